@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+
+/// Schema-less value of a node or edge property.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    /// Milliseconds since the unix epoch.
+    Timestamp(i64),
+    List(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_timestamp(&self) -> Option<i64> {
+        match self {
+            Value::Timestamp(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(l) => Some(l),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Timestamp(t) => write!(f, "{}", t),
+            Value::List(l) => {
+                write!(f, "[")?;
+                for (i, v) in l.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Named properties, ordered by name.
+pub type Properties = BTreeMap<String, Value>;
+
+/// Display colour of a node or edge.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// Opacity between 0 and 1.
+    pub a: f32,
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 1. }
+    }
+}
+
+/// Time interval in which a node or edge exists, an open bound extends to infinity.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Spell {
+    pub start: Option<Value>,
+    pub end: Option<Value>,
+}
+
+/// Value of a property during a spell, for properties that change over time.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedValue {
+    pub name: String,
+    pub value: Value,
+    pub spell: Spell,
+}
+
+/// Descriptive data of a node. It is stored beside the numeric [`crate::Node`] data, which is the
+/// only part the renderer and the physics consume.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeAttributes {
+    /// Identifier in the file the node was read from, written back where the format has ids.
+    pub id: Option<String>,
+    pub label: Option<String>,
+    /// Type or category of the node.
+    pub kind: Option<String>,
+    pub properties: Properties,
+    /// Values of the properties that change over time, the property holds the last of them.
+    pub timeline: Vec<TimedValue>,
+    pub color: Option<Color>,
+    pub size: Option<f32>,
+    /// Intervals in which the node exists in a dynamic graph, empty if it always exists.
+    pub spells: Vec<Spell>,
+    /// Named groups the node belongs to, e.g. the clusters of a DOT file.
+    pub groups: Vec<String>,
+}
+
+/// Descriptive data of an edge, stored beside the [`crate::Edge`] topology and weight.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeAttributes {
+    /// Identifier in the file the edge was read from, written back where the format has ids.
+    pub id: Option<String>,
+    /// Relation type, e.g. "references", "is-a" or "part-of".
+    pub relation: Option<String>,
+    pub label: Option<String>,
+    pub properties: Properties,
+    /// Values of the properties that change over time, the property holds the last of them.
+    pub timeline: Vec<TimedValue>,
+    pub color: Option<Color>,
+    pub thickness: Option<f32>,
+    /// Intervals in which the edge exists in a dynamic graph, empty if it always exists.
+    pub spells: Vec<Spell>,
+}
@@ -0,0 +1,187 @@
+//! Barnes–Hut approximation of the repulsion between all pairs of nodes.
+//!
+//! The nodes are sorted into an octree whose cells know how many nodes they hold and where their
+//! center of mass is. A cell that looks small from a node, its width divided by its distance below
+//! `theta`, repels the node as a single body. A `theta` of zero gives the exact forces, larger
+//! values trade accuracy for speed. This is the CPU reference of the octree pass in
+//! `physics.comp`, it uses the same force law.
+//!
+//! The GPU tree differs in two ways: it has a fixed depth instead of splitting cells down to a few
+//! nodes, and its centers of mass are quantised to 1/1023 of the cell width. Both trees sum the
+//! leaves they open pair by pair, so both are exact at a `theta` of zero, and nodes crowded into
+//! one GPU leaf still repel each other.
+
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Scale of the repulsion between two nodes, as used by the physics shaders.
+pub const REPULSION_SCALE: f32 = 0.00001;
+
+/// Nodes closer than this (squared) do not repel each other, to avoid huge forces.
+const MIN_DISTANCE_SQUARED: f32 = 0.0001;
+
+/// Cells with at most this many nodes are not split any further.
+const LEAF_SIZE: usize = 4;
+
+/// Depth at which cells stop being split, so coincident nodes end up in one leaf.
+const MAX_DEPTH: usize = 24;
+
+struct Cell {
+    center: Vec3,
+    half_width: f32,
+    mass: f32,
+    center_of_mass: Vec3,
+    /// Index of the first child, children are stored next to each other. Zero for leaves.
+    children: u32,
+    /// Nodes of a leaf, as a range into [`Octree::order`].
+    start: u32,
+    end: u32,
+}
+
+impl Cell {
+    fn contains(&self, p: Vec3) -> bool {
+        ((p - self.center).abs().cmple(Vec3::splat(self.half_width))).all()
+    }
+}
+
+pub struct Octree {
+    positions: Vec<Vec3>,
+    /// Node indices ordered so that the nodes of every cell are contiguous.
+    order: Vec<u32>,
+    cells: Vec<Cell>,
+}
+
+impl Octree {
+    pub fn new(positions: &[Vec3]) -> Octree {
+        let mut tree = Octree {
+            positions: positions.to_vec(),
+            order: (0..positions.len() as u32).collect(),
+            cells: vec![],
+        };
+        if positions.is_empty() {
+            return tree;
+        }
+
+        let min = positions.iter().copied().fold(Vec3::INFINITY, Vec3::min);
+        let max = positions.iter().copied().fold(Vec3::NEG_INFINITY, Vec3::max);
+        let half_width = (max - min).max_element() * 0.5 + f32::EPSILON;
+        tree.cells.push(Cell {
+            center: (min + max) * 0.5,
+            half_width,
+            mass: 0.,
+            center_of_mass: Vec3::ZERO,
+            children: 0,
+            start: 0,
+            end: positions.len() as u32,
+        });
+        tree.split(0, 0);
+        tree
+    }
+
+    /// Computes the mass of the cell and splits it into eight children if it holds too many nodes.
+    fn split(&mut self, cell: usize, depth: usize) {
+        let (start, end, center, half_width) = {
+            let c = &self.cells[cell];
+            (c.start as usize, c.end as usize, c.center, c.half_width)
+        };
+
+        let nodes = &mut self.order[start..end];
+        let sum = nodes.iter().map(|i| self.positions[*i as usize]).sum::<Vec3>();
+        self.cells[cell].mass = nodes.len() as f32;
+        self.cells[cell].center_of_mass = sum / nodes.len() as f32;
+
+        if nodes.len() <= LEAF_SIZE || depth == MAX_DEPTH {
+            return;
+        }
+
+        let octant = |p: Vec3| (p.x > center.x) as usize | ((p.y > center.y) as usize) << 1 | ((p.z > center.z) as usize) << 2;
+        nodes.sort_unstable_by_key(|i| octant(self.positions[*i as usize]));
+
+        let first_child = self.cells.len();
+        self.cells[cell].children = first_child as u32;
+        let mut child_start = start;
+        for o in 0..8 {
+            let count = self.order[child_start..end].iter().take_while(|i| octant(self.positions[**i as usize]) == o).count();
+            let offset = Vec3::new(
+                if o & 1 != 0 { 1. } else { -1. },
+                if o & 2 != 0 { 1. } else { -1. },
+                if o & 4 != 0 { 1. } else { -1. },
+            );
+            self.cells.push(Cell {
+                center: center + offset * half_width * 0.5,
+                half_width: half_width * 0.5,
+                mass: 0.,
+                center_of_mass: Vec3::ZERO,
+                children: 0,
+                start: child_start as u32,
+                end: (child_start + count) as u32,
+            });
+            child_start += count;
+        }
+
+        for o in 0..8 {
+            if self.cells[first_child + o].start != self.cells[first_child + o].end {
+                self.split(first_child + o, depth + 1);
+            }
+        }
+    }
+
+    /// Repulsion acting on a node at `p`, which may be one of the nodes in the tree.
+    pub fn force(&self, p: Vec3, repulsion: f32, theta: f32) -> Vec3 {
+        let mut force = Vec3::ZERO;
+        if self.cells.is_empty() {
+            return force;
+        }
+        let strength = REPULSION_SCALE * repulsion * repulsion;
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let cell = &self.cells[index];
+            if cell.mass == 0. {
+                continue;
+            }
+
+            if cell.children == 0 {
+                for i in &self.order[cell.start as usize..cell.end as usize] {
+                    force += pair(p, self.positions[*i as usize], strength);
+                }
+                continue;
+            }
+
+            // Cells around the node are never approximated, they contain its own mass
+            let diff = cell.center_of_mass - p;
+            let width = cell.half_width * 2.;
+            if !cell.contains(p) && width * width < theta * theta * diff.length_squared() {
+                force += pair(p, cell.center_of_mass, strength * cell.mass);
+            } else {
+                stack.extend(cell.children as usize..cell.children as usize + 8);
+            }
+        }
+        force
+    }
+}
+
+/// Force that a body at `q` exerts on a node at `p`.
+pub(crate) fn pair(p: Vec3, q: Vec3, strength: f32) -> Vec3 {
+    let diff = q - p;
+    let l2 = diff.length_squared();
+    if l2 >= MIN_DISTANCE_SQUARED {
+        -diff * strength / l2
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// Approximate repulsion on every node, computed in parallel.
+pub fn repulsion(positions: &[Vec3], repulsion: f32, theta: f32) -> Vec<Vec3> {
+    let tree = Octree::new(positions);
+    positions.par_iter().map(|p| tree.force(*p, repulsion, theta)).collect()
+}
+
+/// Exact repulsion on every node, summed over all pairs.
+pub fn repulsion_exact(positions: &[Vec3], repulsion: f32) -> Vec<Vec3> {
+    let strength = REPULSION_SCALE * repulsion * repulsion;
+    positions.par_iter()
+        .map(|p| positions.iter().map(|q| pair(*p, *q, strength)).sum())
+        .collect()
+}
@@ -0,0 +1,127 @@
+//! Command line modes that run without opening a window.
+
+use std::path::PathBuf;
+use std::time::Instant;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use mycelia::{CpuLayout, LayoutEngine, RepulsionMode, World};
+
+const LAYOUT_USAGE: &str = "\
+usage: mycelia layout <input> -o <output> [options]
+
+Lays out a GraphML, GEXF, DOT, CSV or TSV graph on the CPU and writes it as GraphML, GEXF or DOT.
+
+options:
+  -o, --output <path>       where to write the laid out graph
+  --iterations <n>          simulation steps to run, 1000 by default
+  --seed <n>                start from random positions drawn with this seed instead of the
+                            positions in the input
+  --threshold <distance>    stop early once no node moves further than this in a step
+  --repulsion <mode>        all-pairs (default), barnes-hut or neighbours";
+
+/// Rows of statistics printed while the layout runs.
+const REPORTS: usize = 10;
+
+struct LayoutOptions {
+    input: PathBuf,
+    output: PathBuf,
+    iterations: usize,
+    seed: Option<u64>,
+    threshold: Option<f32>,
+    repulsion: RepulsionMode,
+}
+
+fn parse_layout(args: &[String]) -> Result<LayoutOptions, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut iterations = 1000;
+    let mut seed = None;
+    let mut threshold = None;
+    let mut repulsion = RepulsionMode::AllPairs;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "--iterations" => iterations = value()?.parse().map_err(|e| format!("invalid iterations: {}", e))?,
+            "--seed" => seed = Some(value()?.parse().map_err(|e| format!("invalid seed: {}", e))?),
+            "--threshold" => threshold = Some(value()?.parse().map_err(|e| format!("invalid threshold: {}", e))?),
+            "--repulsion" => repulsion = match value()?.as_str() {
+                "all-pairs" => RepulsionMode::AllPairs,
+                "barnes-hut" => RepulsionMode::BarnesHut,
+                "neighbours" => RepulsionMode::SpatialHash,
+                mode => return Err(format!("unknown repulsion mode '{}'", mode)),
+            },
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            path => return Err(format!("unexpected argument '{}'", path)),
+        }
+    }
+
+    Ok(LayoutOptions {
+        input: input.ok_or("no input graph given")?,
+        output: output.ok_or("no output path given")?,
+        iterations,
+        seed,
+        threshold,
+        repulsion,
+    })
+}
+
+/// Runs `mycelia layout`, returns the exit code of the process.
+pub fn layout(args: &[String]) -> i32 {
+    let options = match parse_layout(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, LAYOUT_USAGE);
+            return 2;
+        }
+    };
+
+    let mut world = match World::load(&options.input) {
+        Ok(world) => world,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", options.input.display(), e);
+            return 1;
+        }
+    };
+    if let Some(seed) = options.seed {
+        world.randomize_with(&mut StdRng::seed_from_u64(seed));
+    }
+    println!("{}: {} nodes, {} edges", options.input.display(), world.node_count(), world.edge_count());
+
+    let mut layout = CpuLayout::new(&mut world);
+    layout.parameters.repulsion_mode = options.repulsion;
+
+    let start = Instant::now();
+    let report_every = options.iterations.div_ceil(REPORTS).max(1);
+    let mut iterations = 0;
+    let mut settled = false;
+    println!("{:>10} {:>14} {:>14}", "iteration", "energy", "largest step");
+    while iterations < options.iterations && !settled {
+        layout.run(&mut (), 1);
+        iterations += 1;
+
+        let energy = layout.energy().unwrap_or_default();
+        settled = options.threshold.is_some_and(|threshold| energy.max_displacement < threshold);
+        if iterations % report_every == 0 || iterations == options.iterations || settled {
+            println!("{:>10} {:>14.6e} {:>14.6e}", iterations, energy.kinetic_energy, energy.max_displacement);
+        }
+    }
+
+    let seconds = start.elapsed().as_secs_f32();
+    if settled {
+        println!("Settled after {} iterations in {:.2}s", iterations, seconds);
+    } else {
+        println!("Ran {} iterations in {:.2}s", iterations, seconds);
+    }
+
+    layout.read_positions(&mut (), &mut world);
+    if let Err(e) = world.save(&options.output) {
+        eprintln!("Failed to write {}: {}", options.output.display(), e);
+        return 1;
+    }
+    println!("Wrote {}", options.output.display());
+    0
+}
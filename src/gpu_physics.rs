@@ -1,8 +1,10 @@
+use std::collections::{HashMap, VecDeque};
 use std::ops::Div;
 use std::process::exit;
 use ash::vk;
 use ash::vk::{BufferUsageFlags, DescriptorBufferInfo, DeviceSize, Image, ImageView, PushConstantRange, ShaderStageFlags, WriteDescriptorSet};
 use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
 use cen::graphics::pipeline_store::{PipelineConfig, PipelineKey};
 use cen::graphics::Renderer;
 use cen::graphics::renderer::RenderComponent;
@@ -11,11 +13,10 @@ use cen::vulkan::PipelineErr::ShaderCompilation;
 use glam::{IVec3, IVec4, Vec3, Vec4};
 use gpu_allocator::MemoryLocation;
 use petgraph::matrix_graph::Nullable;
-use rand::{random, Rng, SeedableRng};
 use log::error;
 use petgraph::visit::{EdgeCount, IntoEdges, NodeCount};
-use rand::rngs::StdRng;
-use crate::world::World;
+use crate::layout::LayoutEngine;
+use crate::world::{Changes, EdgeId, NodeId, World};
 
 #[derive(Debug)]
 #[derive(Copy, Clone)]
@@ -25,13 +26,35 @@ struct Node {
     edge_id: i32,
     velocity: Vec3,
     density: f32,
+    flags: u32,
+    // std430 rounds the size of the struct up to the 16 byte alignment of its vec3 members
+    padding: [u32; 3],
+}
+
+/// Bits of [`Node::flags`].
+const NODE_SELECTED: u32 = 1;
+/// Pinned nodes are not integrated by the physics shaders.
+const NODE_PINNED: u32 = 2;
+
+fn node_flags(world: &World, id: NodeId) -> u32 {
+    let mut flags = 0;
+    if world.is_selected(id) {
+        flags |= NODE_SELECTED;
+    }
+    if world.is_pinned(id) {
+        flags |= NODE_PINNED;
+    }
+    flags
 }
 
 #[derive(Debug)]
 #[derive(Copy, Clone)]
+#[repr(C)]
 struct Edge {
     node0: u32,
     node1: u32,
+    weight: f32,
+    relation: u32,
 }
 
 struct Pipeline {
@@ -39,19 +62,183 @@ struct Pipeline {
     pipeline: PipelineKey,
 }
 
+/// A host visible copy of the node buffer, the event is set by the GPU once the copy is done.
+struct Readback {
+    buffer: Option<Buffer>,
+    event: vk::Event,
+    pending: Option<PendingReadback>,
+}
+
+struct PendingReadback {
+    nodes: usize,
+    /// Copies taken before the world was edited no longer match the node slots.
+    generation: u64,
+    frame: usize,
+}
+
+/// A host visible buffer the energy pass writes its per workgroup sums to.
+struct EnergyReadback {
+    buffer: Option<Buffer>,
+    event: vk::Event,
+    pending: Option<PendingEnergy>,
+}
+
+struct PendingEnergy {
+    workgroups: usize,
+    frame: usize,
+}
+
+/// Motion of the nodes during one simulation step, see [`PhysicsComponent::poll_energy`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StepEnergy {
+    /// Kinetic energy summed over all nodes.
+    pub kinetic_energy: f32,
+    /// Longest distance a node moved.
+    pub max_displacement: f32,
+}
+
+/// Smallest amount of elements the node and edge buffers are allocated with.
+const MIN_CAPACITY: usize = 64;
+
+/// Number of readbacks that can be in flight at once.
+const READBACK_SLOTS: usize = 3;
+
+/// Number of energy reductions that can be in flight at once.
+const ENERGY_SLOTS: usize = 3;
+/// Steps kept in [`PhysicsComponent::energy_history`].
+const ENERGY_HISTORY: usize = 1000;
+/// Steps in a row that have to stay below the threshold before the simulation stops, so a
+/// single slow step does not stop it.
+const CALM_STEPS: usize = 30;
+
+/// Level of the leaves of the Barnes–Hut octree, the root is level 0. The tree does not adapt to
+/// the nodes, leaves that are too close to be approximated are summed pair by pair instead.
+///
+/// Cells sum the positions of their nodes quantised to 1023 steps, which would wrap a 32 bit word
+/// after about 4.2M nodes in one cell. `octree_insert.comp` carries the sums into a second word,
+/// so only the 32 bit node count of a cell limits the tree.
+const OCTREE_DEPTH: usize = 6;
+/// Cells of the complete octree, level `k` has `8^k` cells.
+const OCTREE_CELLS: usize = ((1 << (3 * (OCTREE_DEPTH + 1))) - 1) / 7;
+/// Leaves of the octree, the nodes are sorted into them with the passes of the spatial hash.
+const OCTREE_LEAVES: usize = 1 << (3 * OCTREE_DEPTH);
+/// Size of a cell in `octree_insert.comp`, a count and the summed position in two words per axis.
+const OCTREE_CELL_SIZE: usize = 32;
+/// Lower and upper corner of the box around the nodes in `octree_bounds.comp`.
+const BOUNDS_SIZE: DeviceSize = 32;
+
+/// How the nodes repel each other.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RepulsionMode {
+    /// Every node repels every other node, quadratic in the node count.
+    AllPairs,
+    /// All pairs approximated with an octree, see [`crate::barnes_hut`].
+    BarnesHut,
+    /// Only nodes within [`PhysicsParameters::cutoff`] repel each other, found through a spatial hash grid.
+    SpatialHash,
+}
+
+/// Coefficients of the force model and the integrator, all pushed to the physics shaders.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhysicsParameters {
+    pub repulsion_mode: RepulsionMode,
+    pub repulsion: f32,
+    pub edge_attraction: f32,
+    /// Pull of every node towards the origin, grows with its distance.
+    pub center_attraction: f32,
+    /// Accuracy of the Barnes–Hut approximation, zero is exact.
+    pub theta: f32,
+    /// Distance beyond which nodes do not repel each other with the spatial hash, also the size of its cells.
+    pub cutoff: f32,
+    /// Duration of one simulation step.
+    pub time_step: f32,
+    /// Mass of every node, heavier nodes accelerate slower.
+    pub mass: f32,
+    /// Fraction of the velocity lost every step, one moves the nodes by the force alone.
+    pub damping: f32,
+    /// Factor the temperature is multiplied with after every step.
+    pub cooling: f32,
+    /// Longest distance a node moves in one step at full temperature.
+    pub max_displacement: f32,
+}
+
+impl Default for PhysicsParameters {
+    fn default() -> Self {
+        Self {
+            repulsion_mode: RepulsionMode::AllPairs,
+            repulsion: 1.2,
+            edge_attraction: 2.0,
+            center_attraction: 0.011,
+            theta: 0.5,
+            cutoff: 0.1,
+            time_step: 1.,
+            mass: 1.,
+            damping: 0.6,
+            cooling: 0.999,
+            max_displacement: 0.05,
+        }
+    }
+}
+
 pub struct PhysicsComponent {
     node_count: usize,
     edge_count: usize,
+    node_capacity: usize,
+    edge_capacity: usize,
     node_buffer_a: Option<Buffer>,
     node_buffer_b: Option<Buffer>,
     edge_buffer: Option<Buffer>,
+    /// World edge of every entry in the edge buffer, both directions of an edge map to it.
+    edge_ids: Vec<EdgeId>,
     descriptorsetlayout: Option<DescriptorSetLayout>,
     physics_pipeline: Option<Pipeline>,
     edge_pipeline: Option<Pipeline>,
-    repulsion: f32,
-    pub edge_attraction: f32,
+    bounds_pipeline: Option<Pipeline>,
+    insert_pipeline: Option<Pipeline>,
+    barnes_hut_pipeline: Option<Pipeline>,
+    grid_count_pipeline: Option<Pipeline>,
+    /// Counts the nodes per octree leaf instead of per hash cell.
+    leaf_count_pipeline: Option<Pipeline>,
+    grid_scan_pipeline: Option<Pipeline>,
+    grid_sort_pipeline: Option<Pipeline>,
+    spatial_hash_pipeline: Option<Pipeline>,
+    energy_pipeline: Option<Pipeline>,
+    octree_buffer: Option<Buffer>,
+    bounds_buffer: Option<Buffer>,
+    /// Start of every hash cell or octree leaf in the sorted nodes, with one extra entry at the end.
+    grid_cell_buffer: Option<Buffer>,
+    /// Hash cell of every node and its position within that cell.
+    grid_entry_buffer: Option<Buffer>,
+    /// Node indices sorted by hash cell.
+    grid_sorted_buffer: Option<Buffer>,
+    grid_cells: usize,
+    pub parameters: PhysicsParameters,
+    /// Scales the longest step, cools down over time and is reset by editing the world.
+    pub temperature: f32,
     pub running: bool,
+    /// Runs a single step during the next frame, also while not running.
     pub step: bool,
+    /// Simulation steps run every frame while running.
+    pub steps_per_frame: usize,
+    /// Stop running once no node moves further than [`PhysicsComponent::convergence_threshold`].
+    pub auto_stop: bool,
+    /// Longest step of any node below which the layout counts as settled.
+    pub convergence_threshold: f32,
+    /// Whether the simulation was stopped because it settled, editing the world starts it again.
+    converged: bool,
+    calm_steps: usize,
+    energies: Vec<EnergyReadback>,
+    energy_history: VecDeque<StepEnergy>,
+    /// Parameters of the previous frame, changing any of them restarts a settled simulation.
+    settings: Option<(PhysicsParameters, f32)>,
+    /// Copy the layout back every this many frames, never if zero.
+    pub readback_interval: usize,
+    readback_requested: bool,
+    readbacks: Vec<Readback>,
+    generation: u64,
+    /// Counts the uploads that moved node slots or rebuilt the edge buffer.
+    slot_generation: u64,
+    frame: usize,
 }
 
 #[derive(Pod, Zeroable)]
@@ -60,103 +247,771 @@ pub struct PhysicsComponent {
 #[derive(Clone)]
 struct PushConstants {
     nodes: u32,
-    repulsion: f32
+    repulsion: f32,
+    theta: f32,
+    cutoff: f32,
+    cells: u32,
+    time_step: f32,
+    mass: f32,
+    damping: f32,
+    max_step: f32,
+    edge_attraction: f32,
+    center_attraction: f32,
 }
 
 impl PhysicsComponent {
-    pub(crate) fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             running: true,
             step: false,
-            node_count: 10000,
-            edge_count: 9000,
-            repulsion: 1.2,
-            edge_attraction: 2.0,
+            steps_per_frame: 1,
+            node_count: 0,
+            edge_count: 0,
+            node_capacity: 0,
+            edge_capacity: 0,
             node_buffer_a: None,
             node_buffer_b: None,
             edge_buffer: None,
+            edge_ids: vec![],
             physics_pipeline: None,
             edge_pipeline: None,
+            bounds_pipeline: None,
+            insert_pipeline: None,
+            barnes_hut_pipeline: None,
+            grid_count_pipeline: None,
+            leaf_count_pipeline: None,
+            grid_scan_pipeline: None,
+            grid_sort_pipeline: None,
+            spatial_hash_pipeline: None,
+            energy_pipeline: None,
+            octree_buffer: None,
+            bounds_buffer: None,
+            grid_cell_buffer: None,
+            grid_entry_buffer: None,
+            grid_sorted_buffer: None,
+            grid_cells: 0,
+            parameters: PhysicsParameters::default(),
+            temperature: 1.,
+            auto_stop: true,
+            convergence_threshold: 0.0001,
+            converged: false,
+            calm_steps: 0,
+            energies: vec![],
+            energy_history: VecDeque::new(),
+            settings: None,
             descriptorsetlayout: None,
+            readback_interval: 0,
+            readback_requested: false,
+            readbacks: vec![],
+            generation: 0,
+            slot_generation: 0,
+            frame: 0,
         }
     }
 
     pub fn update_weights(&mut self, world: &World) {
         let mut guard_a = self.node_buffer_a.as_mut().unwrap().mapped().unwrap();
         let (_, node_mem_a, _) = unsafe { guard_a.as_mut_slice().align_to_mut::<Node>() };
-        for (i, node) in world.nodes().iter().enumerate() {
+        for (i, (_, node)) in world.nodes().take(self.node_count).enumerate() {
             node_mem_a[i].density = node.level;
         }
         let mut guard_b = self.node_buffer_b.as_mut().unwrap().mapped().unwrap();
         let (_, node_mem_b, _) = unsafe { guard_b.as_mut_slice().align_to_mut::<Node>() };
-        for (i, node) in world.nodes().iter().enumerate() {
+        for (i, (_, node)) in world.nodes().take(self.node_count).enumerate() {
             node_mem_b[i].density = node.level;
         }
     }
 
-    pub fn set_nodes(&mut self, world: &World) {
+    /// Uploads the complete graph of the world.
+    pub fn set_nodes(&mut self, renderer: &mut Renderer, world: &World) {
+        self.reserve(renderer, world.node_count(), world.edge_count());
+        self.apply_changes(world, &Changes::everything(world));
+    }
+
+    /// Uploads the edits made to the world since the last sync.
+    pub fn sync(&mut self, renderer: &mut Renderer, world: &mut World) {
+        let changes = world.take_changes();
+        if !changes.moved.is_empty() || !changes.nodes.is_empty() || changes.edges || changes.pinned {
+            self.reheat();
+        }
+        if !changes.is_empty() {
+            self.reserve(renderer, world.node_count(), world.edge_count());
+            self.apply_changes(world, &changes);
+        }
+    }
+
+    fn apply_changes(&mut self, world: &World, changes: &Changes) {
+        self.generation += 1;
+        if !changes.moved.is_empty() || changes.edges {
+            self.slot_generation += 1;
+        }
+        let previous_count = self.node_count;
         self.node_count = world.node_count();
-        self.edge_count = world.edge_count();
 
         let mut guard_a = self.node_buffer_a.as_mut().unwrap().mapped().unwrap();
         let (_, node_mem_a, _) = unsafe { guard_a.as_mut_slice().align_to_mut::<Node>() };
-        for (i, node) in world.nodes().iter().enumerate() {
-            node_mem_a[i] = Node {
-                density: node.level as f32,
-                position: node_mem_a[i].position,
-                edge_id: 0,
-                velocity: Vec3::ZERO,
-            };
-        }
         let mut guard_b = self.node_buffer_b.as_mut().unwrap().mapped().unwrap();
         let (_, node_mem_b, _) = unsafe { guard_b.as_mut_slice().align_to_mut::<Node>() };
-        for (i, node) in world.nodes().iter().enumerate() {
-            node_mem_b[i] = Node {
-                density: node.level as f32,
-                position: node_mem_b[i].position,
-                edge_id: 0,
-                velocity: Vec3::ZERO,
+
+        // Nodes that were moved into a freed slot keep their simulated state
+        for (from, to) in changes.moved.iter() {
+            node_mem_a[*to] = node_mem_a[*from];
+            node_mem_b[*to] = node_mem_b[*from];
+        }
+
+        for slot in changes.nodes.iter() {
+            let id = world.node_at_slot(*slot).unwrap();
+            let node = world.node(id).unwrap();
+            // Edge offsets of existing nodes stay valid unless the edges are rebuilt below
+            let edge_id = if *slot < previous_count { node_mem_a[*slot].edge_id } else { 0 };
+            let gpu_node = Node {
+                density: node.level,
+                position: node.pos,
+                edge_id,
+                velocity: node.vel,
+                flags: node_flags(world, id),
+                padding: [0; 3],
             };
+            node_mem_a[*slot] = gpu_node;
+            node_mem_b[*slot] = gpu_node;
         }
 
+        if changes.selection || changes.pinned {
+            for (slot, (id, _)) in world.nodes().enumerate() {
+                node_mem_a[slot].flags = node_flags(world, id);
+                node_mem_b[slot].flags = node_flags(world, id);
+            }
+        }
+
+        if !changes.edges {
+            return;
+        }
+
+        self.edge_count = world.edge_count();
+
+        // Edges refer to the dense node slots
         let mut edges = vec![];
-        for (i, edge) in world.edges().iter().enumerate() {
-            edges.push(Edge {
-                node0: edge.source().index() as u32,
-                node1: edge.target().index() as u32,
-            });
+        for (id, edge) in world.edges() {
+            edges.push((Edge {
+                node0: world.node_slot(edge.source).unwrap() as u32,
+                node1: world.node_slot(edge.target).unwrap() as u32,
+                weight: edge.weight,
+                relation: world.relation_index(id),
+            }, id));
         }
 
         // Add the reverse edges as well
-        let mut reverse_edges = edges.clone().iter().map(|edge| {
-            Edge {
+        let mut reverse_edges = edges.clone().iter().map(|(edge, id)| {
+            (Edge {
                 node0: edge.node1,
-                node1: edge.node0
-            }
-        }).collect::<Vec<Edge>>();
+                node1: edge.node0,
+                ..*edge
+            }, *id)
+        }).collect::<Vec<(Edge, EdgeId)>>();
         edges.append(&mut reverse_edges);
 
         // Sort by starting node
-        edges.sort_by(|a, b| a.node0.cmp(&b.node0));
+        edges.sort_by(|(a, _), (b, _)| a.node0.cmp(&b.node0));
+        let (edges, edge_ids): (Vec<Edge>, Vec<EdgeId>) = edges.into_iter().unzip();
+        self.edge_ids = edge_ids;
 
         let mut edge_guard = self.edge_buffer.as_mut().unwrap().mapped().unwrap();
         let (_, edge_mem, _) = unsafe { edge_guard.as_mut_slice().align_to_mut::<Edge>() };
         for (i,e) in edges.iter().enumerate() {
             edge_mem[i] = *e;
         }
+        // Terminate the list, so the last node's edge loop stops at the end of the buffer
+        edge_mem[edges.len()] = Edge {
+            node0: u32::MAX,
+            node1: u32::MAX,
+            weight: 0.,
+            relation: 0,
+        };
 
         // Update nodes
+        for i in 0..self.node_count {
+            node_mem_a[i].edge_id = 0;
+            node_mem_b[i].edge_id = 0;
+        }
         edges.iter().enumerate().rev().for_each(|(i, edge)| {
             node_mem_a[edge.node0 as usize].edge_id = (i as u32 + 1) as i32;
             node_mem_b[edge.node0 as usize].edge_id = (i as u32 + 1) as i32;
         });
     }
 
+    /// Copies the simulated positions back into the world, waits until the GPU is idle.
+    ///
+    /// Pending edits of the world have to be synced first, otherwise the slots do not match.
+    pub fn read_positions(&mut self, renderer: &mut Renderer, world: &mut World) {
+        unsafe { renderer.device.handle().device_wait_idle().unwrap(); }
+
+        // The node pass writes the newest positions to buffer b
+        let mut guard = self.node_buffer_b.as_mut().unwrap().mapped().unwrap();
+        let (_, node_mem, _) = unsafe { guard.as_mut_slice().align_to_mut::<Node>() };
+        world.apply_layout(node_mem[..self.node_count].iter().map(|n| (n.position, n.velocity)));
+    }
+
+    /// Copies the layout back during the next frame, see [`PhysicsComponent::poll_readback`].
+    pub fn request_readback(&mut self) {
+        self.readback_requested = true;
+    }
+
+    /// Applies the newest finished readback to the world without waiting for the GPU, returns
+    /// whether the world was updated.
+    pub fn poll_readback(&mut self, renderer: &Renderer, world: &mut World) -> bool {
+        let device = renderer.device.handle();
+        let mut newest: Option<(usize, PendingReadback)> = None;
+        for (i, readback) in self.readbacks.iter_mut().enumerate() {
+            let done = readback.pending.is_some() && unsafe { device.get_event_status(readback.event) }.unwrap_or(false);
+            if !done {
+                continue;
+            }
+            let pending = readback.pending.take().unwrap();
+            if pending.generation == self.generation && newest.as_ref().is_none_or(|(_, n)| n.frame < pending.frame) {
+                newest = Some((i, pending));
+            }
+        }
+
+        let Some((i, pending)) = newest else { return false; };
+        let mut guard = self.readbacks[i].buffer.as_mut().unwrap().mapped().unwrap();
+        let (_, node_mem, _) = unsafe { guard.as_mut_slice().align_to_mut::<Node>() };
+        world.apply_layout(node_mem[..pending.nodes].iter().map(|n| (n.position, n.velocity)));
+        true
+    }
+
+    /// Records a copy of the node buffer into a free readback slot, returns false if all slots
+    /// are still in flight.
+    fn record_readback(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer) -> bool {
+        let Some(slot) = self.readbacks.iter().position(|r| r.pending.is_none()) else { return false; };
+        if self.node_count == 0 {
+            return true;
+        }
+
+        let size = (size_of::<Node>() * self.node_count) as DeviceSize;
+        let readback = &mut self.readbacks[slot];
+        if readback.buffer.as_ref().is_none_or(|b| b.size() < size) {
+            readback.buffer = Some(Buffer::new(
+                &renderer.device,
+                &mut renderer.allocator,
+                MemoryLocation::GpuToCpu,
+                (size_of::<Node>() * self.node_capacity) as DeviceSize,
+                BufferUsageFlags::TRANSFER_DST
+            ));
+        }
+        let source = self.node_buffer_b.as_ref().unwrap();
+        let destination = readback.buffer.as_ref().unwrap();
+
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::AccessFlags::TRANSFER_READ,
+            vk::DependencyFlags::default(),
+            size,
+            0,
+            source
+        );
+
+        let device = renderer.device.handle();
+        unsafe {
+            device.reset_event(readback.event).unwrap();
+            device.cmd_copy_buffer(
+                command_buffer.handle(),
+                source.binding().buffer,
+                destination.binding().buffer,
+                &[vk::BufferCopy::default().size(size)]
+            );
+        }
+
+        // Make the copy visible to the host and keep the next frame from overwriting the source early
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::HOST_READ,
+            vk::DependencyFlags::default(),
+            size,
+            0,
+            destination
+        );
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::TRANSFER_READ,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::DependencyFlags::default(),
+            size,
+            0,
+            source
+        );
+        unsafe { device.cmd_set_event(command_buffer.handle(), readback.event, vk::PipelineStageFlags::TRANSFER); }
+
+        readback.pending = Some(PendingReadback {
+            nodes: self.node_count,
+            generation: self.generation,
+            frame: self.frame,
+        });
+        true
+    }
+
+    /// Lets the nodes move at full speed again, restarts the simulation if it stopped because it
+    /// settled.
+    pub fn reheat(&mut self) {
+        self.temperature = 1.;
+        self.calm_steps = 0;
+        if self.converged {
+            self.converged = false;
+            self.running = true;
+        }
+    }
+
+    /// Whether the simulation stopped because the layout settled.
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+
+    /// Energy of the most recent steps, oldest first.
+    pub fn energy_history(&self) -> &VecDeque<StepEnergy> {
+        &self.energy_history
+    }
+
+    fn settings(&self) -> (PhysicsParameters, f32) {
+        (self.parameters, self.convergence_threshold)
+    }
+
+    /// Adds the finished energy reductions to the history without waiting for the GPU and stops
+    /// the simulation once it has settled.
+    pub fn poll_energy(&mut self, renderer: &Renderer) {
+        let device = renderer.device.handle();
+        let mut finished = vec![];
+        for (i, readback) in self.energies.iter_mut().enumerate() {
+            let done = readback.pending.is_some() && unsafe { device.get_event_status(readback.event) }.unwrap_or(false);
+            if done {
+                finished.push((i, readback.pending.take().unwrap()));
+            }
+        }
+        finished.sort_by_key(|(_, pending)| pending.frame);
+
+        for (i, pending) in finished {
+            let mut guard = self.energies[i].buffer.as_mut().unwrap().mapped().unwrap();
+            let (_, partials, _) = unsafe { guard.as_mut_slice().align_to_mut::<[f32; 2]>() };
+            let energy = partials[..pending.workgroups].iter().fold(StepEnergy::default(), |e, [sum, max]| StepEnergy {
+                kinetic_energy: e.kinetic_energy + sum,
+                max_displacement: e.max_displacement.max(*max),
+            });
+
+            if self.energy_history.len() == ENERGY_HISTORY {
+                self.energy_history.pop_front();
+            }
+            self.energy_history.push_back(energy);
+
+            if !self.running || !self.auto_stop {
+                continue;
+            }
+            if energy.max_displacement < self.convergence_threshold {
+                self.calm_steps += 1;
+            } else {
+                self.calm_steps = 0;
+            }
+            if self.calm_steps >= CALM_STEPS {
+                self.running = false;
+                self.converged = true;
+            }
+        }
+    }
+
+    /// Records the reduction of the step that was just simulated into a free energy slot,
+    /// skipped if all slots are still in flight.
+    fn record_energy(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, descriptor_sets: &[WriteDescriptorSet]) {
+        let Some(slot) = self.energies.iter().position(|r| r.pending.is_none()) else { return; };
+        if self.node_count == 0 {
+            return;
+        }
+
+        let workgroups = self.node_count.div_ceil(128);
+        let push_constants = self.push_constants();
+        let readback = &mut self.energies[slot];
+        if readback.buffer.as_ref().is_none_or(|b| b.size() < (size_of::<[f32; 2]>() * workgroups) as DeviceSize) {
+            readback.buffer = Some(Buffer::new(
+                &renderer.device,
+                &mut renderer.allocator,
+                MemoryLocation::GpuToCpu,
+                (size_of::<[f32; 2]>() * self.node_capacity.div_ceil(128)) as DeviceSize,
+                BufferUsageFlags::STORAGE_BUFFER
+            ));
+        }
+        let destination = readback.buffer.as_ref().unwrap();
+        let event = readback.event;
+
+        // The node pass has to finish writing buffer b
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::AccessFlags::SHADER_READ,
+            vk::DependencyFlags::default(),
+            vk::WHOLE_SIZE,
+            0,
+            self.node_buffer_b.as_ref().unwrap()
+        );
+
+        unsafe { renderer.device.handle().reset_event(event).unwrap(); }
+
+        let energy_buffer_bindings = [destination.binding()];
+        let mut energy_descriptor_sets = descriptor_sets.to_vec();
+        energy_descriptor_sets.push(WriteDescriptorSet::default()
+            .dst_binding(7)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&energy_buffer_bindings));
+
+        let compute = renderer.pipeline_store().get(self.energy_pipeline.as_ref().unwrap().pipeline).unwrap();
+        command_buffer.bind_pipeline(&compute);
+        command_buffer.bind_push_descriptor(&compute, 0, &energy_descriptor_sets);
+        command_buffer.push_constants(
+            &compute,
+            ShaderStageFlags::COMPUTE,
+            0,
+            bytemuck::bytes_of(&push_constants)
+        );
+        command_buffer.dispatch(workgroups as u32, 1, 1);
+
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::HOST,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::AccessFlags::HOST_READ,
+            vk::DependencyFlags::default(),
+            vk::WHOLE_SIZE,
+            0,
+            destination
+        );
+        unsafe { renderer.device.handle().cmd_set_event(command_buffer.handle(), event, vk::PipelineStageFlags::COMPUTE_SHADER); }
+
+        self.energies[slot].pending = Some(PendingEnergy {
+            workgroups,
+            frame: self.frame,
+        });
+    }
+
+    fn push_constants(&self) -> PushConstants {
+        let parameters = &self.parameters;
+        PushConstants {
+            nodes: self.node_count as u32,
+            repulsion: parameters.repulsion,
+            theta: parameters.theta,
+            cutoff: parameters.cutoff,
+            cells: match parameters.repulsion_mode {
+                RepulsionMode::BarnesHut => OCTREE_LEAVES as u32,
+                _ => self.grid_cells as u32,
+            },
+            time_step: parameters.time_step,
+            mass: parameters.mass,
+            damping: parameters.damping,
+            max_step: parameters.max_displacement * self.temperature,
+            edge_attraction: parameters.edge_attraction,
+            center_attraction: parameters.center_attraction,
+        }
+    }
+
+    /// Records the passes that sort the nodes in buffer a into the spatial hash grid, or into the
+    /// leaves of the octree for Barnes–Hut.
+    fn record_grid(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, descriptor_sets: &[WriteDescriptorSet]) {
+        let cells = self.grid_cell_buffer.as_ref().unwrap();
+
+        // The previous frame's node pass reads the cells
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::DependencyFlags::default(),
+            vk::WHOLE_SIZE,
+            0,
+            cells
+        );
+        unsafe {
+            renderer.device.handle().cmd_fill_buffer(command_buffer.handle(), cells.binding().buffer, 0, vk::WHOLE_SIZE, 0);
+        }
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            vk::DependencyFlags::default(),
+            vk::WHOLE_SIZE,
+            0,
+            cells
+        );
+
+        let push_constants = self.push_constants();
+        let node_dispatches = self.node_count.div_ceil(128) as u32;
+
+        // Count the nodes per cell, turn the counts into offsets, then scatter the nodes
+        let entries = self.grid_entry_buffer.as_ref().unwrap();
+        let sorted = self.grid_sorted_buffer.as_ref().unwrap();
+        let count = match self.parameters.repulsion_mode {
+            RepulsionMode::BarnesHut => &self.leaf_count_pipeline,
+            _ => &self.grid_count_pipeline,
+        };
+        for (pipeline, dispatches, written) in [
+            (count, node_dispatches, &[cells, entries][..]),
+            (&self.grid_scan_pipeline, 1, &[cells][..]),
+            (&self.grid_sort_pipeline, node_dispatches, &[sorted][..]),
+        ] {
+            let compute = renderer.pipeline_store().get(pipeline.as_ref().unwrap().pipeline).unwrap();
+            command_buffer.bind_pipeline(&compute);
+            command_buffer.bind_push_descriptor(&compute, 0, descriptor_sets);
+            command_buffer.push_constants(
+                &compute,
+                ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(&push_constants)
+            );
+            command_buffer.dispatch(dispatches, 1, 1);
+
+            for buffer in written {
+                command_buffer.buffer_barrier(
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::AccessFlags::SHADER_WRITE,
+                    vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                    vk::DependencyFlags::default(),
+                    vk::WHOLE_SIZE,
+                    0,
+                    buffer
+                );
+            }
+        }
+    }
+
+    /// Records the passes that build the Barnes–Hut octree from the positions in buffer a.
+    fn record_octree(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, descriptor_sets: &[WriteDescriptorSet]) {
+        let octree = self.octree_buffer.as_ref().unwrap();
+        let bounds = self.bounds_buffer.as_ref().unwrap();
+        let device = renderer.device.handle();
+
+        // The previous frame's node pass reads the octree
+        for buffer in [octree, bounds] {
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::DependencyFlags::default(),
+                vk::WHOLE_SIZE,
+                0,
+                buffer
+            );
+        }
+        unsafe {
+            device.cmd_fill_buffer(command_buffer.handle(), octree.binding().buffer, 0, vk::WHOLE_SIZE, 0);
+            // Empty bounds, every node lowers the lower corner and raises the upper one
+            device.cmd_fill_buffer(command_buffer.handle(), bounds.binding().buffer, 0, BOUNDS_SIZE / 2, u32::MAX);
+            device.cmd_fill_buffer(command_buffer.handle(), bounds.binding().buffer, BOUNDS_SIZE / 2, BOUNDS_SIZE / 2, 0);
+        }
+        for buffer in [octree, bounds] {
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                vk::DependencyFlags::default(),
+                vk::WHOLE_SIZE,
+                0,
+                buffer
+            );
+        }
+
+        let push_constants = self.push_constants();
+        let dispatches = self.node_count.div_ceil(128) as u32;
+
+        for (pipeline, written) in [(&self.bounds_pipeline, bounds), (&self.insert_pipeline, octree)] {
+            let compute = renderer.pipeline_store().get(pipeline.as_ref().unwrap().pipeline).unwrap();
+            command_buffer.bind_pipeline(&compute);
+            command_buffer.bind_push_descriptor(&compute, 0, descriptor_sets);
+            command_buffer.push_constants(
+                &compute,
+                ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(&push_constants)
+            );
+            command_buffer.dispatch(dispatches, 1, 1);
+
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+                vk::DependencyFlags::default(),
+                vk::WHOLE_SIZE,
+                0,
+                written
+            );
+        }
+    }
+
+    /// Records one simulation step, the edge pass, the octree or grid if needed, the node pass and
+    /// optionally the energy reduction.
+    fn record_step(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, energy: bool) {
+        // The previous step reads buffer a and writes buffer b, the edge pass does the opposite
+        for buffer in [self.node_buffer_a.as_ref().unwrap(), self.node_buffer_b.as_ref().unwrap()] {
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                vk::DependencyFlags::default(),
+                vk::WHOLE_SIZE,
+                0,
+                buffer
+            );
+        }
+
+        let buffer_bindings_a = [self.node_buffer_a.as_ref().unwrap().binding()];
+        let buffer_write_descriptor_set_a = WriteDescriptorSet::default()
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&buffer_bindings_a);
+
+        let buffer_bindings_b = [self.node_buffer_b.as_ref().unwrap().binding()];
+        let buffer_write_descriptor_set_b = WriteDescriptorSet::default()
+            .dst_binding(1)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&buffer_bindings_b);
+
+        let edge_buffer_bindings = [self.edge_buffer.as_ref().unwrap().binding()];
+        let edge_buffer_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(2)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&edge_buffer_bindings);
+
+        // Edge physics
+        {
+            let compute = renderer.pipeline_store().get(self.edge_pipeline.as_ref().unwrap().pipeline).unwrap();
+
+            command_buffer.bind_pipeline(&compute);
+
+            // Reads from buffer b and writes to buffer a
+            command_buffer.bind_push_descriptor(
+                &compute,
+                0,
+                &[buffer_write_descriptor_set_a, buffer_write_descriptor_set_b, edge_buffer_write_descriptor_set]
+            );
+
+            let push_constants = self.push_constants();
+            command_buffer.push_constants(
+                &compute,
+                ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(&push_constants)
+            );
+
+            let dispatches = self.node_count.div_ceil(128);
+            command_buffer.dispatch(dispatches as u32, 1, 1 );
+
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+                vk::DependencyFlags::default(),
+                self.node_buffer_a.as_mut().unwrap().size(),
+                0,
+                self.node_buffer_a.as_ref().unwrap()
+            );
+        }
+
+        let octree_buffer_bindings = [self.octree_buffer.as_ref().unwrap().binding()];
+        let octree_buffer_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(2)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&octree_buffer_bindings);
+
+        let bounds_buffer_bindings = [self.bounds_buffer.as_ref().unwrap().binding()];
+        let bounds_buffer_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(3)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&bounds_buffer_bindings);
+
+        let grid_buffer_bindings = [
+            self.grid_cell_buffer.as_ref().unwrap().binding(),
+            self.grid_entry_buffer.as_ref().unwrap().binding(),
+            self.grid_sorted_buffer.as_ref().unwrap().binding(),
+        ];
+        let grid_buffer_write_descriptor_sets = [4, 5, 6].map(|binding| {
+            WriteDescriptorSet::default()
+                .dst_binding(binding)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(std::slice::from_ref(&grid_buffer_bindings[binding as usize - 4]))
+        });
+
+        let node_descriptor_sets = [
+            buffer_write_descriptor_set_a,
+            buffer_write_descriptor_set_b,
+            octree_buffer_write_descriptor_set,
+            bounds_buffer_write_descriptor_set,
+            grid_buffer_write_descriptor_sets[0],
+            grid_buffer_write_descriptor_sets[1],
+            grid_buffer_write_descriptor_sets[2],
+        ];
+
+        match self.parameters.repulsion_mode {
+            RepulsionMode::AllPairs => {}
+            RepulsionMode::BarnesHut => {
+                self.record_octree(renderer, command_buffer, &node_descriptor_sets);
+                self.record_grid(renderer, command_buffer, &node_descriptor_sets);
+            }
+            RepulsionMode::SpatialHash => self.record_grid(renderer, command_buffer, &node_descriptor_sets),
+        }
+
+        // Node physics
+        {
+            let pipeline = match self.parameters.repulsion_mode {
+                RepulsionMode::AllPairs => &self.physics_pipeline,
+                RepulsionMode::BarnesHut => &self.barnes_hut_pipeline,
+                RepulsionMode::SpatialHash => &self.spatial_hash_pipeline,
+            };
+            let compute = renderer.pipeline_store().get(pipeline.as_ref().unwrap().pipeline).unwrap();
+
+            command_buffer.bind_pipeline(&compute);
+
+            command_buffer.bind_push_descriptor(
+                &compute,
+                0,
+                &node_descriptor_sets
+            );
+
+            let push_constants = self.push_constants();
+            command_buffer.push_constants(
+                &compute,
+                ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(&push_constants)
+            );
+
+            let dispatches = self.node_count.div_ceil(128);
+            command_buffer.dispatch(dispatches as u32, 1, 1 );
+        }
+
+        if energy {
+            self.record_energy(renderer, command_buffer, &node_descriptor_sets);
+        }
+
+        self.temperature *= self.parameters.cooling;
+    }
+
     pub fn node_buffer(&self) -> DescriptorBufferInfo {
         self.node_buffer_a.as_ref().unwrap().binding()
     }
 
-    fn load_pipeline(renderer: &mut Renderer, path: &str, layout: DescriptorSetLayout, push_constant_range: PushConstantRange) -> PipelineKey {
+    fn load_pipeline(renderer: &mut Renderer, path: &str, layout: DescriptorSetLayout, push_constant_range: PushConstantRange, macros: HashMap<String, String>) -> PipelineKey {
         match renderer.pipeline_store().insert(PipelineConfig {
             shader_path: path.into(),
             descriptor_set_layouts: vec![
@@ -165,7 +1020,7 @@ impl PhysicsComponent {
             push_constant_ranges: vec![
                 push_constant_range
             ],
-            macros: Default::default(),
+            macros,
         }) {
             Ok(x) => x,
             Err(ShaderCompilation(x)) => {
@@ -187,107 +1042,108 @@ impl PhysicsComponent {
         self.edge_count * 2
     }
 
-    pub fn repulsion(&mut self) -> &mut f32 {
-        &mut self.repulsion
+    /// Changes whenever node slots move or the edge buffer is rebuilt, after which slots and edge
+    /// entries of earlier frames may belong to other nodes and edges.
+    pub fn slot_generation(&self) -> u64 {
+        self.slot_generation
     }
 
-    fn create_buffers(&mut self, renderer: &mut Renderer) {
-
-        let mut rng = StdRng::seed_from_u64(3243451135u64);
+    /// The world edge behind an entry of the edge buffer.
+    pub fn edge_at(&self, entry: usize) -> Option<EdgeId> {
+        self.edge_ids.get(entry).copied()
+    }
 
-        let mut node_buffer_a = Buffer::new(
+    fn allocate<T>(renderer: &mut Renderer, count: usize) -> Buffer {
+        Buffer::new(
             &renderer.device,
             &mut renderer.allocator,
             MemoryLocation::CpuToGpu,
-            (size_of::<Node>() * self.node_count) as DeviceSize,
-            BufferUsageFlags::STORAGE_BUFFER
-        );
+            (size_of::<T>() * count) as DeviceSize,
+            BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::TRANSFER_SRC
+        )
+    }
 
-        let mut node_buffer_b = Buffer::new(
+    /// A buffer that is only used by the shaders and cleared with transfers.
+    fn allocate_device<T>(renderer: &mut Renderer, count: usize) -> Buffer {
+        Buffer::new(
             &renderer.device,
             &mut renderer.allocator,
-            MemoryLocation::CpuToGpu,
-            (size_of::<Node>() * self.node_count) as DeviceSize,
-            BufferUsageFlags::STORAGE_BUFFER
-        );
+            MemoryLocation::GpuOnly,
+            (size_of::<T>() * count) as DeviceSize,
+            BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::TRANSFER_DST
+        )
+    }
 
-        // Copy start positions to node buffer
-        {
-            let mut guard_a = node_buffer_a.mapped().unwrap();
-            let (_, node_mem, _) = unsafe { guard_a.as_mut_slice().align_to_mut::<Node>() };
-            for i in 0..self.node_count {
-                node_mem[i] = Node {
-                    position: Vec3::new(rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()) * 0.2 - 0.1,
-                    edge_id: 0,
-                    velocity: Vec3::ZERO,
-                    density: 0.,
-                    // position: Vec3::new(1., 1., 1.) * i as f32 / self.node_count as f32 * 0.2 - 0.1,
-                };
-            }
+    /// Replaces the buffer with one that holds `capacity` elements, keeping the first `keep` elements.
+    fn reallocate<T: Copy>(renderer: &mut Renderer, buffer: &mut Option<Buffer>, keep: usize, capacity: usize) {
+        let mut new_buffer = Self::allocate::<T>(renderer, capacity);
+        if let Some(old_buffer) = buffer.as_mut() {
+            let mut old_guard = old_buffer.mapped().unwrap();
+            let (_, old_mem, _) = unsafe { old_guard.as_mut_slice().align_to_mut::<T>() };
+            let mut new_guard = new_buffer.mapped().unwrap();
+            let (_, new_mem, _) = unsafe { new_guard.as_mut_slice().align_to_mut::<T>() };
+            new_mem[..keep].copy_from_slice(&old_mem[..keep]);
         }
+        *buffer = Some(new_buffer);
+    }
 
-        self.node_buffer_a = Some(node_buffer_a);
-        self.node_buffer_b = Some(node_buffer_b);
-
-        let mut edge_buffer = Buffer::new(
-            &renderer.device,
-            &mut renderer.allocator,
-            MemoryLocation::CpuToGpu,
-            (size_of::<Edge>() * self.edge_count * 2) as DeviceSize,
-            BufferUsageFlags::STORAGE_BUFFER
-        );
+    fn grown_capacity(capacity: usize, required: usize) -> usize {
+        let mut capacity = capacity.max(MIN_CAPACITY);
+        while capacity < required {
+            capacity *= 2;
+        }
+        capacity
+    }
 
-        // Copy edges
-        let mut edges = vec![Edge {node0: 0, node1: 1}];
-        for i in 0..self.edge_count {
-            edges.push(Edge {
-                node0: edges[(rng.gen::<u32>() % edges.len() as u32) as usize].node1,
-                node1: edges.len() as u32 - 1,
-            });
-        };
+    /// Grows the buffers geometrically until they fit the given amount of nodes and edges.
+    pub fn reserve(&mut self, renderer: &mut Renderer, nodes: usize, edges: usize) {
+        let node_capacity = Self::grown_capacity(self.node_capacity, nodes);
+        let edge_capacity = Self::grown_capacity(self.edge_capacity, edges * 2);
+        self.resize(renderer, node_capacity, edge_capacity);
+    }
 
-        // Add the reverse edges as well
-        let mut reverse_edges = edges.clone().iter().map(|edge| {
-            Edge {
-                node0: edge.node1,
-                node1: edge.node0
-            }
-        }).collect::<Vec<Edge>>();
-        edges.append(&mut reverse_edges);
+    /// Shrinks the buffers to the size of the uploaded graph.
+    pub fn shrink_to_fit(&mut self, renderer: &mut Renderer) {
+        let node_capacity = self.node_count.max(MIN_CAPACITY);
+        let edge_capacity = (self.edge_count * 2).max(MIN_CAPACITY);
+        self.resize(renderer, node_capacity, edge_capacity);
+    }
 
-        // Sort by starting node
-        edges.sort_by(|a, b| a.node0.cmp(&b.node0));
+    /// Amount of nodes and edges the buffers can hold without being reallocated.
+    pub fn capacity(&self) -> (usize, usize) {
+        (self.node_capacity, self.edge_capacity / 2)
+    }
 
-        {
-            let mut edge_guard = edge_buffer.mapped().unwrap();
-            let (_, edge_mem, _) = unsafe { edge_guard.as_mut_slice().align_to_mut::<Edge>() };
-            for i in 0..(self.edge_count * 2) {
-                edge_mem[i] = edges[i];
-            }
+    fn resize(&mut self, renderer: &mut Renderer, node_capacity: usize, edge_capacity: usize) {
+        if node_capacity == self.node_capacity && edge_capacity == self.edge_capacity {
+            return;
         }
 
-        // Set node positions to zero
-        let mut guard_a = self.node_buffer_a.as_mut().unwrap().mapped().unwrap();
-        let (_, node_mem, _) = unsafe { guard_a.as_mut_slice().align_to_mut::<Node>() };
-        node_mem.iter_mut().enumerate().rev().for_each(|(i, node)| {
-            //node.position = Vec4::ZERO;
-            node.position = Vec3::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5);
-        });
+        // The old buffers might still be used by frames in flight
+        if self.node_buffer_a.is_some() {
+            unsafe { renderer.device.handle().device_wait_idle().unwrap(); }
+        }
 
-        // Update nodes
-        edges.iter().enumerate().rev().for_each(|(i, edge)| {
-            node_mem[edge.node0 as usize].edge_id = (i as u32 + 1) as i32;
-            node_mem[edge.node0 as usize].position = Vec3::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5);
-        });
+        if node_capacity != self.node_capacity {
+            let keep = self.node_count.min(node_capacity);
+            Self::reallocate::<Node>(renderer, &mut self.node_buffer_a, keep, node_capacity);
+            Self::reallocate::<Node>(renderer, &mut self.node_buffer_b, keep, node_capacity);
+            self.node_capacity = node_capacity;
 
-        // Copy buffer a into the backbuffer
-        let mut guard_b = self.node_buffer_b.as_mut().unwrap().mapped().unwrap();
-        let (_, node_mem_b, _) = unsafe { guard_b.as_mut_slice().align_to_mut::<Node>() };
-        node_mem.iter().enumerate().for_each(|(i, n)| {
-            node_mem_b[i] = node_mem[i];
-        });
+            // The hash table has about twice as many cells as there are nodes, the same buffer
+            // holds the octree leaves
+            self.grid_cells = (node_capacity * 2).next_power_of_two();
+            self.grid_cell_buffer = Some(Self::allocate_device::<u32>(renderer, self.grid_cells.max(OCTREE_LEAVES) + 1));
+            self.grid_entry_buffer = Some(Self::allocate_device::<[u32; 2]>(renderer, node_capacity));
+            self.grid_sorted_buffer = Some(Self::allocate_device::<u32>(renderer, node_capacity));
+        }
 
-        self.edge_buffer = Some(edge_buffer);
+        if edge_capacity != self.edge_capacity {
+            // One extra entry for the terminating edge
+            let keep = (self.edge_count * 2 + 1).min(edge_capacity + 1);
+            Self::reallocate::<Edge>(renderer, &mut self.edge_buffer, keep, edge_capacity + 1);
+            self.edge_capacity = edge_capacity;
+        }
     }
 
     fn create_edge_pipeline(&mut self, renderer: &mut Renderer) {
@@ -320,7 +1176,7 @@ impl PhysicsComponent {
             .size(size_of::<PushConstants>() as u32);
 
         // Pipeline
-        let pipeline = Self::load_pipeline(renderer, "shaders/physics_edges.comp", descriptorset.clone(), push_constant_range);
+        let pipeline = Self::load_pipeline(renderer, "shaders/physics_edges.comp", descriptorset.clone(), push_constant_range, HashMap::new());
 
         self.edge_pipeline = Some(Pipeline{
             pipeline,
@@ -351,6 +1207,26 @@ impl PhysicsComponent {
                 .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
                 .descriptor_count(1)
                 .stage_flags(vk::ShaderStageFlags::COMPUTE ),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(4)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(5)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(6)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(7)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
         ];
         let descriptorset = DescriptorSetLayout::new_push_descriptor(
             &renderer.device,
@@ -363,109 +1239,110 @@ impl PhysicsComponent {
             .size(size_of::<PushConstants>() as u32);
 
         // Pipeline
-        let pipeline = Self::load_pipeline(renderer, "shaders/physics.comp", descriptorset.clone(), push_constant_range);
+        let pipeline = Self::load_pipeline(renderer, "shaders/physics.comp", descriptorset.clone(), push_constant_range, HashMap::new());
 
         self.physics_pipeline = Some(Pipeline {
             pipeline,
-            descriptorsetlayout: descriptorset
+            descriptorsetlayout: descriptorset.clone()
         });
-    }
-}
 
-impl RenderComponent for PhysicsComponent {
-    fn initialize(&mut self, renderer: &mut Renderer) {
-        self.create_buffers(renderer);
-        self.create_physics_pipeline(renderer);
-        self.create_edge_pipeline(renderer);
+        // Barnes–Hut and spatial hash variants, with the passes that build their octree and grid
+        let depth = HashMap::from([("DEPTH".to_string(), OCTREE_DEPTH.to_string())]);
+        let mut barnes_hut = depth.clone();
+        barnes_hut.insert("BARNES_HUT".to_string(), String::new());
+        let mut leaves = depth.clone();
+        leaves.insert("OCTREE_LEAVES".to_string(), String::new());
+        let spatial_hash = HashMap::from([("SPATIAL_HASH".to_string(), String::new())]);
+        for (path, macros, target) in [
+            ("shaders/octree_bounds.comp", HashMap::new(), &mut self.bounds_pipeline),
+            ("shaders/octree_insert.comp", depth, &mut self.insert_pipeline),
+            ("shaders/physics.comp", barnes_hut, &mut self.barnes_hut_pipeline),
+            ("shaders/grid_count.comp", HashMap::new(), &mut self.grid_count_pipeline),
+            ("shaders/grid_count.comp", leaves, &mut self.leaf_count_pipeline),
+            ("shaders/grid_scan.comp", HashMap::new(), &mut self.grid_scan_pipeline),
+            ("shaders/grid_sort.comp", HashMap::new(), &mut self.grid_sort_pipeline),
+            ("shaders/physics.comp", spatial_hash, &mut self.spatial_hash_pipeline),
+            ("shaders/energy.comp", HashMap::new(), &mut self.energy_pipeline),
+        ] {
+            *target = Some(Pipeline {
+                pipeline: Self::load_pipeline(renderer, path, descriptorset.clone(), push_constant_range, macros),
+                descriptorsetlayout: descriptorset.clone(),
+            });
+        }
     }
+}
 
-    fn render(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, swapchain_image: &Image, swapchain_image_view: &ImageView) {
-
-        let buffer_bindings_a = [self.node_buffer_a.as_ref().unwrap().binding()];
-        let buffer_write_descriptor_set_a = WriteDescriptorSet::default()
-            .dst_binding(0)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-            .buffer_info(&buffer_bindings_a);
-
-        let buffer_bindings_b = [self.node_buffer_b.as_ref().unwrap().binding()];
-        let buffer_write_descriptor_set_b = WriteDescriptorSet::default()
-            .dst_binding(1)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-            .buffer_info(&buffer_bindings_b);
+impl LayoutEngine for PhysicsComponent {
+    type Context = Renderer;
 
-        let edge_buffer_bindings = [self.edge_buffer.as_ref().unwrap().binding()];
-        let edge_buffer_write_descriptor_set = WriteDescriptorSet::default()
-            .dst_binding(2)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-            .buffer_info(&edge_buffer_bindings);
+    fn sync(&mut self, renderer: &mut Renderer, world: &mut World) {
+        PhysicsComponent::sync(self, renderer, world);
+    }
 
-        // Edge physics
-        {
-            let compute = renderer.pipeline_store().get(self.edge_pipeline.as_ref().unwrap().pipeline).unwrap();
+    /// Records the steps into a command buffer of their own and waits for it, for use outside of
+    /// the render loop.
+    fn run(&mut self, renderer: &mut Renderer, steps: usize) {
+        let mut command_buffer = CommandBuffer::new(&renderer.device, &renderer.command_pool, false);
+        command_buffer.begin();
+        for i in 0..steps {
+            self.record_step(renderer, &mut command_buffer, i + 1 == steps);
+        }
+        command_buffer.end();
+        renderer.device.submit_single_time_command(renderer.queue, &command_buffer);
+        self.poll_energy(renderer);
+    }
 
-            command_buffer.bind_pipeline(&compute);
+    fn read_positions(&mut self, renderer: &mut Renderer, world: &mut World) {
+        PhysicsComponent::read_positions(self, renderer, world);
+    }
 
-            // Reads from buffer b and writes to buffer a
-            command_buffer.bind_push_descriptor(
-                &compute,
-                0,
-                &[buffer_write_descriptor_set_a, buffer_write_descriptor_set_b, edge_buffer_write_descriptor_set]
-            );
+    fn energy(&self) -> Option<StepEnergy> {
+        self.energy_history.back().copied()
+    }
 
-            let push_constants = PushConstants {
-                nodes: self.node_count as u32,
-                repulsion: self.edge_attraction,
-            };
-            command_buffer.push_constants(
-                &compute,
-                ShaderStageFlags::COMPUTE,
-                0,
-                bytemuck::bytes_of(&push_constants)
-            );
+    fn parameters(&mut self) -> &mut PhysicsParameters {
+        &mut self.parameters
+    }
+}
 
-            let dispatches = self.node_count.div_ceil(128);
-            command_buffer.dispatch(dispatches as u32, 1, 1 );
+impl RenderComponent for PhysicsComponent {
+    fn initialize(&mut self, renderer: &mut Renderer) {
+        self.resize(renderer, MIN_CAPACITY, MIN_CAPACITY);
+        self.readbacks = (0..READBACK_SLOTS).map(|_| Readback {
+            buffer: None,
+            event: unsafe { renderer.device.handle().create_event(&vk::EventCreateInfo::default(), None).unwrap() },
+            pending: None,
+        }).collect();
+        self.energies = (0..ENERGY_SLOTS).map(|_| EnergyReadback {
+            buffer: None,
+            event: unsafe { renderer.device.handle().create_event(&vk::EventCreateInfo::default(), None).unwrap() },
+            pending: None,
+        }).collect();
+        self.octree_buffer = Some(Self::allocate_device::<[u8; OCTREE_CELL_SIZE]>(renderer, OCTREE_CELLS));
+        self.bounds_buffer = Some(Self::allocate_device::<u8>(renderer, BOUNDS_SIZE as usize));
+        self.create_physics_pipeline(renderer);
+        self.create_edge_pipeline(renderer);
+    }
 
-            command_buffer.buffer_barrier(
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                vk::AccessFlags::SHADER_WRITE,
-                vk::AccessFlags::SHADER_READ,
-                vk::DependencyFlags::default(),
-                self.node_buffer_a.as_mut().unwrap().size(),
-                0,
-                self.node_buffer_a.as_ref().unwrap()
-            );
+    fn render(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, swapchain_image: &Image, swapchain_image_view: &ImageView) {
+        // Changing a parameter restarts a simulation that settled
+        let settings = self.settings();
+        if self.settings.is_some_and(|previous| previous != settings) {
+            self.reheat();
         }
+        self.settings = Some(settings);
 
-        // Node physics
-        {
-            let compute = renderer.pipeline_store().get(self.physics_pipeline.as_ref().unwrap().pipeline).unwrap();
-
-            command_buffer.bind_pipeline(&compute);
-
-            command_buffer.bind_push_descriptor(
-                &compute,
-                0,
-                &[buffer_write_descriptor_set_a, buffer_write_descriptor_set_b]
-            );
-
-            let push_constants = PushConstants {
-                nodes: self.node_count as u32,
-                repulsion: self.repulsion,
-            };
-            command_buffer.push_constants(
-                &compute,
-                ShaderStageFlags::COMPUTE,
-                0,
-                bytemuck::bytes_of(&push_constants)
-            );
+        // A single step while paused, otherwise the configured amount, the last one is measured
+        let steps = if self.running { self.steps_per_frame } else if self.step { 1 } else { 0 };
+        for i in 0..steps {
+            self.record_step(renderer, command_buffer, i + 1 == steps);
+        }
+        self.step = false;
 
-            let dispatches = self.node_count.div_ceil(128);
-            command_buffer.dispatch(dispatches as u32, 1, 1 );
+        self.frame += 1;
+        let due = self.readback_interval > 0 && self.frame.is_multiple_of(self.readback_interval);
+        if (self.readback_requested || due) && self.record_readback(renderer, command_buffer) {
+            self.readback_requested = false;
         }
     }
 }
\ No newline at end of file
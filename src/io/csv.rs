@@ -0,0 +1,252 @@
+//! Node tables and edge lists in CSV or TSV files.
+//!
+//! Both files start with a header row, the columns are mapped by name through [`CsvOptions`].
+//! The node id is kept as the id of the node, which the other formats write back. Nodes that are
+//! only referenced in the edge list are created with their id as label. Cells are
+//! read as numbers, booleans or timestamps when they parse as such, empty cells are skipped.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use ::csv::{ReaderBuilder, StringRecord};
+use crate::attributes::{EdgeAttributes, NodeAttributes, Properties, Value};
+use crate::io::{parse_timestamp, Error};
+use crate::world::{Node, NodeId, World};
+
+/// Column mapping of the node table and edge list.
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    delimiter: u8,
+    directed: bool,
+    id: String,
+    label: Option<String>,
+    kind: Option<String>,
+    source: String,
+    target: String,
+    weight: Option<String>,
+    relation: Option<String>,
+    properties: Option<Vec<String>>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            directed: true,
+            id: "id".to_string(),
+            label: Some("label".to_string()),
+            kind: Some("type".to_string()),
+            source: "source".to_string(),
+            target: "target".to_string(),
+            weight: Some("weight".to_string()),
+            relation: Some("relation".to_string()),
+            properties: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Tab separated values with the default columns.
+    pub fn tsv() -> Self {
+        Self::default().delimiter(b'\t')
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn directed(mut self, directed: bool) -> Self {
+        self.directed = directed;
+        self
+    }
+
+    /// Column of the node table that identifies a node, defaults to "id".
+    pub fn id(mut self, column: impl Into<String>) -> Self {
+        self.id = column.into();
+        self
+    }
+
+    pub fn label(mut self, column: Option<&str>) -> Self {
+        self.label = column.map(str::to_string);
+        self
+    }
+
+    pub fn kind(mut self, column: Option<&str>) -> Self {
+        self.kind = column.map(str::to_string);
+        self
+    }
+
+    pub fn source(mut self, column: impl Into<String>) -> Self {
+        self.source = column.into();
+        self
+    }
+
+    pub fn target(mut self, column: impl Into<String>) -> Self {
+        self.target = column.into();
+        self
+    }
+
+    /// Column holding the edge weight, edges weigh 1 without it.
+    pub fn weight(mut self, column: Option<&str>) -> Self {
+        self.weight = column.map(str::to_string);
+        self
+    }
+
+    pub fn relation(mut self, column: Option<&str>) -> Self {
+        self.relation = column.map(str::to_string);
+        self
+    }
+
+    /// Columns that become properties, by default all columns that are not mapped otherwise.
+    pub fn properties<S: Into<String>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.properties = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+fn csv_error(error: ::csv::Error) -> Error {
+    let line = error.position().map(|p| p.line() as usize);
+    let message = error.to_string();
+    match (error.into_kind(), line) {
+        (::csv::ErrorKind::Io(e), _) => Error::Io(e),
+        (_, Some(line)) => Error::Parse { line, message },
+        (_, None) => Error::Invalid(message),
+    }
+}
+
+fn parse_cell(cell: &str) -> Value {
+    if let Ok(number) = cell.parse::<f64>() {
+        return Value::Number(number);
+    }
+    match cell {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => parse_timestamp(cell).map(Value::Timestamp).unwrap_or_else(|| Value::String(cell.to_string())),
+    }
+}
+
+/// Column indices of a table, looked up by name in the header.
+struct Columns {
+    header: StringRecord,
+}
+
+impl Columns {
+    fn required(&self, name: &str, table: &str) -> Result<usize, Error> {
+        self.optional(Some(name)).ok_or_else(|| Error::Invalid(format!("the {} has no '{}' column", table, name)))
+    }
+
+    fn optional(&self, name: Option<&str>) -> Option<usize> {
+        self.header.iter().position(|c| Some(c.trim()) == name)
+    }
+
+    /// The property columns, either the configured ones present in this table or all but the
+    /// mapped ones.
+    fn properties(&self, configured: &Option<Vec<String>>, mapped: &[Option<usize>]) -> Vec<(usize, String)> {
+        match configured {
+            Some(names) => names.iter().filter_map(|name| Some((self.optional(Some(name))?, name.clone()))).collect(),
+            None => self.header.iter()
+                .enumerate()
+                .filter(|(i, _)| !mapped.contains(&Some(*i)))
+                .map(|(i, name)| (i, name.trim().to_string()))
+                .collect(),
+        }
+    }
+}
+
+fn cell(record: &StringRecord, column: Option<usize>) -> Option<&str> {
+    column.and_then(|c| record.get(c)).map(str::trim).filter(|c| !c.is_empty())
+}
+
+fn properties(record: &StringRecord, columns: &[(usize, String)]) -> Properties {
+    columns.iter()
+        .filter_map(|(i, name)| Some((name.clone(), parse_cell(cell(record, Some(*i))?))))
+        .collect()
+}
+
+fn line(record: &StringRecord) -> usize {
+    record.position().map(|p| p.line() as usize).unwrap_or(0)
+}
+
+impl World {
+    /// Builds a world from an optional node table and an edge list.
+    pub fn read_csv(nodes: Option<&str>, edges: &str, options: &CsvOptions) -> Result<World, Error> {
+        let mut world = World::empty();
+        world.set_directed(options.directed);
+        let mut ids: HashMap<String, NodeId> = HashMap::new();
+
+        let builder = || {
+            let mut builder = ReaderBuilder::new();
+            builder.delimiter(options.delimiter);
+            builder
+        };
+
+        if let Some(nodes) = nodes {
+            let mut reader = builder().from_reader(nodes.as_bytes());
+            let columns = Columns { header: reader.headers().map_err(csv_error)?.clone() };
+            let id = columns.required(&options.id, "node table")?;
+            let label = columns.optional(options.label.as_deref());
+            let kind = columns.optional(options.kind.as_deref());
+            let property_columns = columns.properties(&options.properties, &[Some(id), label, kind]);
+
+            for record in reader.records() {
+                let record = record.map_err(csv_error)?;
+                let Some(key) = cell(&record, Some(id)) else {
+                    return Err(Error::Parse { line: line(&record), message: format!("missing node id in column '{}'", options.id) });
+                };
+                if ids.contains_key(key) {
+                    return Err(Error::Parse { line: line(&record), message: format!("duplicate node id '{}'", key) });
+                }
+
+                let attributes = NodeAttributes {
+                    id: Some(key.to_string()),
+                    label: Some(cell(&record, label).unwrap_or(key).to_string()),
+                    kind: cell(&record, kind).map(str::to_string),
+                    properties: properties(&record, &property_columns),
+                    ..NodeAttributes::default()
+                };
+                ids.insert(key.to_string(), world.add_node_with(Node::new_random(0.), attributes));
+            }
+        }
+
+        let mut reader = builder().from_reader(edges.as_bytes());
+        let columns = Columns { header: reader.headers().map_err(csv_error)?.clone() };
+        let source = columns.required(&options.source, "edge list")?;
+        let target = columns.required(&options.target, "edge list")?;
+        let weight = columns.optional(options.weight.as_deref());
+        let relation = columns.optional(options.relation.as_deref());
+        let property_columns = columns.properties(&options.properties, &[Some(source), Some(target), weight, relation]);
+
+        for record in reader.records() {
+            let record = record.map_err(csv_error)?;
+            let mut endpoint = |column: usize, endpoint: &str, name: &str| -> Result<NodeId, Error> {
+                let Some(key) = cell(&record, Some(column)) else {
+                    return Err(Error::Parse { line: line(&record), message: format!("missing {} in column '{}'", endpoint, name) });
+                };
+                Ok(*ids.entry(key.to_string()).or_insert_with(|| {
+                    world.add_node_with(Node::new_random(0.), NodeAttributes { id: Some(key.to_string()), label: Some(key.to_string()), ..NodeAttributes::default() })
+                }))
+            };
+            let source = endpoint(source, "source", &options.source)?;
+            let target = endpoint(target, "target", &options.target)?;
+
+            let weight = match cell(&record, weight) {
+                Some(text) => text.parse::<f32>().map_err(|_| Error::Parse { line: line(&record), message: format!("invalid weight '{}'", text) })?,
+                None => 1.,
+            };
+            let attributes = EdgeAttributes {
+                relation: cell(&record, relation).map(str::to_string),
+                properties: properties(&record, &property_columns),
+                ..EdgeAttributes::default()
+            };
+            world.add_edge_with(source, target, weight, attributes);
+        }
+
+        Ok(world)
+    }
+
+    pub fn load_csv(nodes: Option<&Path>, edges: &Path, options: &CsvOptions) -> Result<World, Error> {
+        let nodes = nodes.map(fs::read_to_string).transpose()?;
+        Self::read_csv(nodes.as_deref(), &fs::read_to_string(edges)?, options)
+    }
+}
@@ -0,0 +1,712 @@
+//! [Graphviz DOT](https://graphviz.org/doc/info/lang.html) reader and writer.
+//!
+//! Node and edge statements, default attributes, subgraphs and edges between subgraphs are
+//! supported. Named subgraphs, including clusters, become node groups. Nodes are labelled with
+//! their name unless they have a `label`, `type` holds the node kind and `relation` the edge
+//! relation. Node names and edge `id`s are kept as ids. Other attributes are kept as string
+//! properties. Quoted strings understand the escapes `\"` and `\\` and the line breaks `\n`,
+//! `\l` and `\r`, other backslashes are kept.
+//!
+//! The writer stores positions as `pos` in points, so a layout can be rendered unchanged with
+//! `neato -n`. The depth is written as `z` unless it is zero, a `pos` with two coordinates and no
+//! `z` places the node at zero depth.
+
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::attributes::{Color, EdgeAttributes, Value};
+use crate::io::{element_ids, Error};
+use crate::world::{Node, NodeId, World};
+
+/// Graphviz positions are in points, a world unit is written as one inch.
+const POINTS_PER_UNIT: f32 = 72.;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Id { text: String, quoted: bool },
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Equals,
+    Semicolon,
+    Comma,
+    Colon,
+    Arrow,
+    Line,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    let mut line_start = true;
+    // A '+' between quoted strings concatenates them
+    let mut concatenate = false;
+
+    let error = |line: usize, message: &str| Error::Parse { line, message: message.to_string() };
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+                line_start = true;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            // Preprocessor output lines
+            '#' if line_start => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let start = line;
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(error(start, "unterminated comment"));
+                }
+                i += 2;
+                continue;
+            }
+            _ => {}
+        }
+        line_start = false;
+
+        let punctuation = match c {
+            '{' => Some(Token::Open),
+            '}' => Some(Token::Close),
+            '[' => Some(Token::OpenBracket),
+            ']' => Some(Token::CloseBracket),
+            '=' => Some(Token::Equals),
+            ';' => Some(Token::Semicolon),
+            ',' => Some(Token::Comma),
+            ':' => Some(Token::Colon),
+            _ => None,
+        };
+        if let Some(token) = punctuation {
+            tokens.push((token, line));
+            i += 1;
+            continue;
+        }
+
+        if concatenate && c != '"' {
+            return Err(error(line, "expected a string after '+'"));
+        }
+
+        let token = match c {
+            '-' if chars.get(i + 1) == Some(&'>') => {
+                i += 2;
+                Token::Arrow
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                i += 2;
+                Token::Line
+            }
+            '"' => {
+                let start = line;
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error(start, "unterminated string")),
+                        Some('"') => break,
+                        // Line continuation
+                        Some('\\') if chars.get(i + 1) == Some(&'\n') => {
+                            line += 1;
+                            i += 1;
+                        }
+                        // The escapes `quote` writes, line breaks as in labels, others are kept
+                        Some('\\') if chars.get(i + 1).is_some() => {
+                            match chars[i + 1] {
+                                '"' => text.push('"'),
+                                '\\' => text.push('\\'),
+                                'n' | 'l' | 'r' => text.push('\n'),
+                                c => {
+                                    text.push('\\');
+                                    text.push(c);
+                                }
+                            }
+                            i += 1;
+                        }
+                        Some(&c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            text.push(c);
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+
+                if concatenate {
+                    if let Some((Token::Id { text: previous, .. }, _)) = tokens.last_mut() {
+                        previous.push_str(&text);
+                    }
+                    concatenate = false;
+                    continue;
+                }
+                Token::Id { text, quoted: true }
+            }
+            '+' if matches!(tokens.last(), Some((Token::Id { quoted: true, .. }, _))) => {
+                concatenate = true;
+                i += 1;
+                continue;
+            }
+            '<' => {
+                let start = line;
+                let mut depth = 0;
+                let mut text = String::new();
+                loop {
+                    let Some(&c) = chars.get(i) else {
+                        return Err(error(start, "unterminated HTML string"));
+                    };
+                    i += 1;
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        '\n' => line += 1,
+                        _ => {}
+                    }
+                    // The outer angle brackets are not part of the text
+                    if depth == 0 {
+                        break;
+                    }
+                    if depth > 1 || c != '<' {
+                        text.push(c);
+                    }
+                }
+                Token::Id { text, quoted: true }
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' || !c.is_ascii() => {
+                let start = i;
+                let numeral = c.is_ascii_digit() || c == '.' || c == '-';
+                i += 1;
+                while i < chars.len() {
+                    let c = chars[i];
+                    let valid = if numeral { c.is_ascii_digit() || c == '.' } else { c.is_alphanumeric() || c == '_' || !c.is_ascii() };
+                    if !valid {
+                        break;
+                    }
+                    i += 1;
+                }
+                Token::Id { text: chars[start..i].iter().collect(), quoted: false }
+            }
+            c => return Err(error(line, &format!("unexpected character '{}'", c))),
+        };
+        tokens.push((token, line));
+    }
+
+    Ok(tokens)
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    // Color lists use the first entry
+    let text = text.split(':').next()?.trim();
+    let text = text.split(';').next()?.trim();
+
+    if let Some(hex) = text.strip_prefix('#') {
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        let a = if hex.len() == 8 { channel(6)? as f32 / 255. } else { 1. };
+        return Some(Color { r: channel(0)?, g: channel(2)?, b: channel(4)?, a });
+    }
+
+    // Hue, saturation and value in [0, 1]
+    let hsv = text.split([',', ' ']).filter(|s| !s.is_empty()).map(|s| s.parse::<f32>()).collect::<Result<Vec<_>, _>>();
+    if let Ok([h, s, v]) = hsv.as_deref() {
+        let (h, s, v) = (h.clamp(0., 1.) * 6., s.clamp(0., 1.), v.clamp(0., 1.));
+        let channel = |n: f32| {
+            let k = (n + h) % 6.;
+            ((v - v * s * k.min(4. - k).clamp(0., 1.)) * 255.).round() as u8
+        };
+        return Some(Color::new(channel(5.), channel(3.), channel(1.)));
+    }
+
+    let (r, g, b) = match text.to_ascii_lowercase().as_str() {
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "green" => (0, 255, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "cyan" => (0, 255, 255),
+        "magenta" => (255, 0, 255),
+        "gray" | "grey" => (192, 192, 192),
+        "lightgray" | "lightgrey" => (211, 211, 211),
+        "darkgray" | "darkgrey" => (169, 169, 169),
+        "orange" => (255, 165, 0),
+        "purple" => (160, 32, 240),
+        "brown" => (165, 42, 42),
+        "pink" => (255, 192, 203),
+        "navy" => (0, 0, 128),
+        "lightblue" => (173, 216, 230),
+        "darkgreen" => (0, 100, 0),
+        "gold" => (255, 215, 0),
+        "transparent" | "invis" | "none" => return Some(Color { r: 255, g: 255, b: 255, a: 0. }),
+        _ => return None,
+    };
+    Some(Color::new(r, g, b))
+}
+
+fn format_color(color: &Color) -> String {
+    if color.a < 1. {
+        format!("#{:02x}{:02x}{:02x}{:02x}", color.r, color.g, color.b, (color.a.clamp(0., 1.) * 255.).round() as u8)
+    } else {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    }
+}
+
+/// Quotes a string so the tokenizer reads it back unchanged.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Names are written bare when they are valid identifiers and not keywords.
+fn quote_name(text: &str) -> String {
+    let keyword = ["node", "edge", "graph", "digraph", "subgraph", "strict"].iter().any(|k| text.eq_ignore_ascii_case(k));
+    let identifier = text.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !keyword;
+    if identifier { text.to_string() } else { quote(text) }
+}
+
+type Attributes = Vec<(String, String)>;
+
+#[derive(Clone, Default)]
+struct Scope {
+    node_defaults: Attributes,
+    edge_defaults: Attributes,
+    groups: Vec<String>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    directed: bool,
+    strict: bool,
+    world: World,
+    ids: HashMap<String, NodeId>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(t, _)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.position).or(self.tokens.last()).map(|(_, l)| *l).unwrap_or(1)
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::Parse { line: self.line(), message: message.into() }
+    }
+
+    fn next(&mut self) -> Result<Token, Error> {
+        let token = self.tokens.get(self.position).map(|(t, _)| t.clone()).ok_or_else(|| self.error("unexpected end of input"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token, description: &str) -> Result<(), Error> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            _ => {
+                self.position -= 1;
+                Err(self.error(format!("expected {}", description)))
+            }
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id { text, quoted: false }) if text.eq_ignore_ascii_case(keyword))
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Id { text, .. } => Ok(text),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected an identifier"))
+            }
+        }
+    }
+
+    fn skip(&mut self, token: Token) -> bool {
+        if self.peek() == Some(&token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn graph(&mut self) -> Result<(), Error> {
+        self.strict = self.is_keyword("strict");
+        if self.strict {
+            self.position += 1;
+        }
+
+        if self.is_keyword("digraph") {
+            self.directed = true;
+        } else if self.is_keyword("graph") {
+            self.directed = false;
+        } else {
+            return Err(self.error("expected 'graph' or 'digraph'"));
+        }
+        self.position += 1;
+        self.world.set_directed(self.directed);
+
+        if matches!(self.peek(), Some(Token::Id { .. })) {
+            self.position += 1;
+        }
+        self.expect(Token::Open, "'{'")?;
+        self.statements(&mut Scope::default())?;
+        self.expect(Token::Close, "'}'")?;
+
+        if self.peek().is_some() {
+            return Err(self.error("unexpected content after the graph"));
+        }
+        Ok(())
+    }
+
+    /// Parses statements up to the closing brace, returns the nodes that appeared in them.
+    fn statements(&mut self, scope: &mut Scope) -> Result<Vec<NodeId>, Error> {
+        let mut nodes = vec![];
+        while !matches!(self.peek(), Some(Token::Close) | None) {
+            self.statement(scope, &mut nodes)?;
+            self.skip(Token::Semicolon);
+        }
+        Ok(nodes)
+    }
+
+    fn statement(&mut self, scope: &mut Scope, nodes: &mut Vec<NodeId>) -> Result<(), Error> {
+        // Default attributes
+        for keyword in ["graph", "node", "edge"] {
+            if self.is_keyword(keyword) && self.tokens.get(self.position + 1).map(|(t, _)| t) == Some(&Token::OpenBracket) {
+                self.position += 1;
+                let attributes = self.attribute_lists()?;
+                match keyword {
+                    "node" => scope.node_defaults.extend(attributes),
+                    "edge" => scope.edge_defaults.extend(attributes),
+                    _ => {}
+                }
+                return Ok(());
+            }
+        }
+
+        // Graph attribute assignment
+        if matches!(self.peek(), Some(Token::Id { .. })) && self.tokens.get(self.position + 1).map(|(t, _)| t) == Some(&Token::Equals) {
+            self.position += 2;
+            self.identifier()?;
+            return Ok(());
+        }
+
+        let mut endpoints = vec![self.endpoint(scope)?];
+        loop {
+            let (expected, other) = if self.directed { (Token::Arrow, Token::Line) } else { (Token::Line, Token::Arrow) };
+            if self.peek() == Some(&other) {
+                return Err(self.error(if self.directed { "'--' in a directed graph" } else { "'->' in an undirected graph" }));
+            }
+            if !self.skip(expected) {
+                break;
+            }
+            endpoints.push(self.endpoint(scope)?);
+        }
+
+        let attributes = if self.peek() == Some(&Token::OpenBracket) { self.attribute_lists()? } else { vec![] };
+
+        if endpoints.len() == 1 {
+            if let Endpoint::Node(id) = endpoints[0] {
+                self.apply_node_attributes(id, &attributes);
+            }
+        } else {
+            let mut edge_attributes = scope.edge_defaults.clone();
+            edge_attributes.extend(attributes);
+            for pair in endpoints.windows(2) {
+                for &source in pair[0].nodes() {
+                    for &target in pair[1].nodes() {
+                        self.add_edge(source, target, &edge_attributes);
+                    }
+                }
+            }
+        }
+
+        for endpoint in endpoints {
+            for &id in endpoint.nodes() {
+                if !nodes.contains(&id) {
+                    nodes.push(id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn endpoint(&mut self, scope: &mut Scope) -> Result<Endpoint, Error> {
+        if self.is_keyword("subgraph") || self.peek() == Some(&Token::Open) {
+            let mut inner = scope.clone();
+            if self.is_keyword("subgraph") {
+                self.position += 1;
+                if matches!(self.peek(), Some(Token::Id { .. })) {
+                    let name = self.identifier()?;
+                    inner.groups.push(name);
+                }
+            }
+            self.expect(Token::Open, "'{'")?;
+            let nodes = self.statements(&mut inner)?;
+            self.expect(Token::Close, "'}'")?;
+            return Ok(Endpoint::Subgraph(nodes));
+        }
+
+        let name = self.identifier()?;
+        // Ports are irrelevant for the layout
+        while self.skip(Token::Colon) {
+            self.identifier()?;
+        }
+        Ok(Endpoint::Node(self.node(&name, scope)))
+    }
+
+    fn node(&mut self, name: &str, scope: &Scope) -> NodeId {
+        let id = match self.ids.get(name) {
+            Some(id) => *id,
+            None => {
+                let id = self.world.add_node(Node::new_random(0.));
+                self.world.set_label(id, name);
+                self.world.attributes_mut(id).unwrap().id = Some(name.to_string());
+                self.ids.insert(name.to_string(), id);
+                self.apply_node_attributes(id, &scope.node_defaults);
+                id
+            }
+        };
+
+        let groups = &mut self.world.attributes_mut(id).unwrap().groups;
+        for group in &scope.groups {
+            if !groups.contains(group) {
+                groups.push(group.clone());
+            }
+        }
+        id
+    }
+
+    fn attribute_lists(&mut self) -> Result<Attributes, Error> {
+        let mut attributes = vec![];
+        while self.skip(Token::OpenBracket) {
+            while !self.skip(Token::CloseBracket) {
+                let key = self.identifier()?;
+                self.expect(Token::Equals, "'='")?;
+                let value = self.identifier()?;
+                attributes.push((key, value));
+                if !self.skip(Token::Comma) {
+                    self.skip(Token::Semicolon);
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    fn apply_node_attributes(&mut self, id: NodeId, attributes: &Attributes) {
+        let mut z = None;
+        for (key, value) in attributes {
+            match key.as_str() {
+                // The default label is the node name
+                "label" if value == "\\N" => {}
+                "label" => self.world.set_label(id, value.clone()),
+                "type" => self.world.set_kind(id, value.as_str()),
+                "color" if parse_color(value).is_some() => self.world.attributes_mut(id).unwrap().color = parse_color(value),
+                "pos" if parse_position(value).is_some() => {
+                    let (position, z) = parse_position(value).unwrap();
+                    // Flat layouts, such as those of neato, lie in the z = 0 plane
+                    let z = z.unwrap_or(0.);
+                    self.world.set_position(id, position.extend(z));
+                }
+                "z" if value.parse::<f32>().is_ok() => z = value.parse::<f32>().ok(),
+                _ => {
+                    self.world.set_property(id, key.clone(), value.as_str());
+                }
+            }
+        }
+
+        if let Some(z) = z {
+            let position = self.world.node(id).unwrap().pos;
+            self.world.set_position(id, position.truncate().extend(z / POINTS_PER_UNIT));
+        }
+    }
+
+    fn add_edge(&mut self, source: NodeId, target: NodeId, attributes: &Attributes) {
+        if self.strict && (self.world.find_edge(source, target).is_some() || (!self.directed && self.world.find_edge(target, source).is_some())) {
+            return;
+        }
+
+        let mut weight = 1.;
+        let mut edge = EdgeAttributes::default();
+        for (key, value) in attributes {
+            match key.as_str() {
+                "id" => edge.id = Some(value.clone()),
+                "label" => edge.label = Some(value.clone()),
+                "relation" => edge.relation = Some(value.clone()),
+                "weight" if value.parse::<f32>().is_ok() => weight = value.parse().unwrap(),
+                "color" if parse_color(value).is_some() => edge.color = parse_color(value),
+                "penwidth" if value.parse::<f32>().is_ok() => edge.thickness = value.parse().ok(),
+                _ => {
+                    edge.properties.insert(key.clone(), Value::from(value.as_str()));
+                }
+            }
+        }
+        self.world.add_edge_with(source, target, weight, edge);
+    }
+}
+
+enum Endpoint {
+    Node(NodeId),
+    Subgraph(Vec<NodeId>),
+}
+
+impl Endpoint {
+    fn nodes(&self) -> &[NodeId] {
+        match self {
+            Endpoint::Node(id) => std::slice::from_ref(id),
+            Endpoint::Subgraph(nodes) => nodes,
+        }
+    }
+}
+
+/// Parses "x,y[,z][!]" in points.
+fn parse_position(text: &str) -> Option<(glam::Vec2, Option<f32>)> {
+    let coordinates = text.trim().trim_end_matches('!')
+        .split(',')
+        .map(|c| c.trim().parse::<f32>().map(|c| c / POINTS_PER_UNIT))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    match coordinates.as_slice() {
+        [x, y] => Some((glam::Vec2::new(*x, *y), None)),
+        [x, y, z] => Some((glam::Vec2::new(*x, *y), Some(*z))),
+        _ => None,
+    }
+}
+
+impl World {
+    /// Builds a world from a DOT document.
+    pub fn read_dot(input: &str) -> Result<World, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            directed: true,
+            strict: false,
+            world: World::empty(),
+            ids: HashMap::new(),
+        };
+        parser.graph()?;
+        Ok(parser.world)
+    }
+
+    pub fn load_dot(path: impl AsRef<Path>) -> Result<World, Error> {
+        Self::read_dot(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the world as a DOT document with `pos` attributes. Nodes keep the names they were
+    /// read with, the others are named by their slot.
+    pub fn write_dot(&self, writer: &mut impl Write) -> Result<(), Error> {
+        let (keyword, operator) = if self.is_directed() { ("digraph", "->") } else { ("graph", "--") };
+        writeln!(writer, "{} {{", keyword)?;
+
+        let names = element_ids(self.nodes().map(|(id, _)| self.attributes(id).unwrap().id.as_deref()), "n")
+            .iter()
+            .map(|name| quote_name(name))
+            .collect::<Vec<_>>();
+
+        for (slot, (id, node)) in self.nodes().enumerate() {
+            let attributes = self.attributes(id).unwrap();
+            let mut list = vec![];
+            if let Some(label) = &attributes.label {
+                list.push(("label".to_string(), quote(label)));
+            }
+            if let Some(kind) = &attributes.kind {
+                list.push(("type".to_string(), quote(kind)));
+            }
+            if let Some(color) = &attributes.color {
+                list.push(("color".to_string(), quote(&format_color(color))));
+            }
+            let position = node.pos * POINTS_PER_UNIT;
+            list.push(("pos".to_string(), quote(&format!("{},{}!", position.x, position.y))));
+            if position.z != 0. {
+                list.push(("z".to_string(), quote(&position.z.to_string())));
+            }
+            for (key, value) in attributes.properties.iter() {
+                list.push((quote_name(key), quote(&value.to_string())));
+            }
+            write_statement(writer, &names[slot], &list)?;
+        }
+
+        for (id, edge) in self.edges() {
+            let attributes = self.edge_attributes(id).unwrap();
+            let mut list = vec![("weight".to_string(), edge.weight.to_string())];
+            if let Some(id) = &attributes.id {
+                list.push(("id".to_string(), quote(id)));
+            }
+            if let Some(label) = &attributes.label {
+                list.push(("label".to_string(), quote(label)));
+            }
+            if let Some(relation) = &attributes.relation {
+                list.push(("relation".to_string(), quote(relation)));
+            }
+            if let Some(color) = &attributes.color {
+                list.push(("color".to_string(), quote(&format_color(color))));
+            }
+            if let Some(thickness) = attributes.thickness {
+                list.push(("penwidth".to_string(), thickness.to_string()));
+            }
+            for (key, value) in attributes.properties.iter() {
+                list.push((quote_name(key), quote(&value.to_string())));
+            }
+            let source = &names[self.node_slot(edge.source).unwrap()];
+            let target = &names[self.node_slot(edge.target).unwrap()];
+            write_statement(writer, &format!("{} {} {}", source, operator, target), &list)?;
+        }
+
+        for group in self.groups() {
+            let members = self.nodes()
+                .enumerate()
+                .filter(|(_, (id, _))| self.attributes(*id).unwrap().groups.iter().any(|g| g == group))
+                .map(|(slot, _)| format!("{};", names[slot]))
+                .collect::<Vec<_>>();
+            writeln!(writer, "  subgraph {} {{ {} }}", quote(group), members.join(" "))?;
+        }
+
+        writeln!(writer, "}}")?;
+        Ok(())
+    }
+
+    pub fn save_dot(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_dot(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn write_statement(writer: &mut impl Write, statement: &str, attributes: &[(String, String)]) -> Result<(), Error> {
+    let list = attributes.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+    writeln!(writer, "  {} [{}];", statement, list.join(", "))?;
+    Ok(())
+}
@@ -0,0 +1,595 @@
+//! [GEXF 1.3](https://gexf.net) reader and writer, the native format of Gephi.
+//!
+//! Attribute values are mapped onto properties, the `viz` position, colour and size onto the node
+//! position and attributes, and the `start`/`end` bounds and `spells` of dynamic graphs onto
+//! [`Spell`]s. A node attribute titled `type` holds the node kind, the edge `kind` holds the
+//! relation type. Attribute values that change over time are kept in the timeline of the node or
+//! edge, the property holds the last of them. The ids of the file are kept and written back.
+
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use glam::Vec3;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use crate::attributes::{Color, EdgeAttributes, NodeAttributes, Properties, Spell, TimedValue, Value};
+use crate::io::{attribute, element_ids, format_timestamp, line_at, parse_timestamp, Error};
+use crate::world::{Node, World};
+
+struct AttributeDefinition {
+    title: String,
+    kind: String,
+    default: Option<String>,
+}
+
+#[derive(Default)]
+struct Viz {
+    color: Option<Color>,
+    position: Option<Vec3>,
+    size: Option<f32>,
+    thickness: Option<f32>,
+}
+
+/// An `attvalue`, the bounds are only given for values that change over time.
+struct AttValue {
+    key: String,
+    value: String,
+    start: Option<String>,
+    end: Option<String>,
+}
+
+#[derive(Default)]
+struct Pending {
+    id: String,
+    label: Option<String>,
+    source: String,
+    target: String,
+    weight: Option<f32>,
+    kind: Option<String>,
+    values: Vec<AttValue>,
+    spells: Vec<(Option<String>, Option<String>)>,
+    viz: Viz,
+}
+
+enum Owner {
+    Node(usize),
+    Edge(usize),
+}
+
+fn parse_value(text: &str, kind: &str) -> Value {
+    let kind = kind.to_ascii_lowercase();
+    if let Some(element) = kind.strip_prefix("list") {
+        let text = text.trim();
+        let items = match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            Some(inner) => inner.split(',').collect::<Vec<_>>(),
+            None => text.split('|').collect::<Vec<_>>(),
+        };
+        return Value::List(items.into_iter()
+            .map(str::trim)
+            .filter(|i| !i.is_empty())
+            .map(|i| parse_value(i, element))
+            .collect());
+    }
+
+    match kind.as_str() {
+        "integer" | "long" | "float" | "double" | "bigdecimal" | "biginteger" | "short" | "byte" => {
+            text.trim().parse::<f64>().map(Value::Number).unwrap_or_else(|_| Value::String(text.to_string()))
+        }
+        "boolean" => match text.trim() {
+            "true" | "1" => Value::Bool(true),
+            "false" | "0" => Value::Bool(false),
+            _ => Value::String(text.to_string()),
+        },
+        "date" | "datetime" | "timestamp" => parse_timestamp(text).map(Value::Timestamp).unwrap_or_else(|| Value::String(text.to_string())),
+        _ => Value::String(text.to_string()),
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "double",
+        Value::Bool(_) => "boolean",
+        Value::Timestamp(_) => "date",
+        Value::String(_) => "string",
+        Value::List(l) if !l.is_empty() && l.iter().all(|v| matches!(v, Value::Number(_))) => "listdouble",
+        Value::List(l) if !l.is_empty() && l.iter().all(|v| matches!(v, Value::Bool(_))) => "listboolean",
+        Value::List(_) => "liststring",
+    }
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Timestamp(t) => format_timestamp(*t),
+        Value::List(l) => format!("[{}]", l.iter().map(format_value).collect::<Vec<_>>().join(", ")),
+        v => v.to_string(),
+    }
+}
+
+/// Collects the attribute definitions of all properties and their timelines, the type is taken
+/// from the first value and falls back to a string when the values disagree.
+fn attribute_types<'a>(values: impl Iterator<Item = (&'a String, &'a Value)>) -> Vec<(String, &'static str)> {
+    let mut types: Vec<(String, &'static str)> = vec![];
+    for (name, value) in values {
+        match types.iter_mut().find(|(n, _)| n == name) {
+            Some((_, kind)) => {
+                if *kind != value_type(value) {
+                    *kind = "string";
+                }
+            }
+            None => types.push((name.clone(), value_type(value))),
+        }
+    }
+    types.sort();
+    types
+}
+
+/// Attribute values of a node or edge with their bounds. A property with a timeline is written as
+/// its timeline, unless the property was changed since.
+fn attribute_values(types: &[(String, &'static str)], properties: &Properties, timeline: &[TimedValue]) -> Vec<(usize, String, Option<Spell>)> {
+    let index = |name: &str| types.iter().position(|(t, _)| t == name).unwrap();
+    let mut values = vec![];
+    for (name, value) in properties.iter() {
+        let changes = timeline.iter().filter(|v| &v.name == name).collect::<Vec<_>>();
+        if changes.last().is_some_and(|last| last.value == *value) {
+            values.extend(changes.into_iter().map(|v| (index(name), format_value(&v.value), Some(v.spell.clone()))));
+        } else {
+            values.push((index(name), format_value(value), None));
+        }
+    }
+    values
+}
+
+fn parse_float(element: &BytesStart, name: &str) -> Result<Option<f32>, quick_xml::Error> {
+    Ok(attribute(element, name)?.and_then(|v| v.trim().parse::<f32>().ok()))
+}
+
+fn parse_color(element: &BytesStart) -> Result<Option<Color>, quick_xml::Error> {
+    if let Some(hex) = attribute(element, "hex")? {
+        let hex = hex.trim_start_matches('#');
+        if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+            let a = parse_float(element, "a")?.unwrap_or(1.);
+            return Ok(Some(Color { r: (rgb >> 16) as u8, g: (rgb >> 8) as u8, b: rgb as u8, a }));
+        }
+    }
+
+    let channel = |name: &str| -> Result<Option<u8>, quick_xml::Error> {
+        Ok(attribute(element, name)?.and_then(|v| v.trim().parse::<u8>().ok()))
+    };
+    match (channel("r")?, channel("g")?, channel("b")?) {
+        (Some(r), Some(g), Some(b)) => Ok(Some(Color { r, g, b, a: parse_float(element, "a")?.unwrap_or(1.) })),
+        _ => Ok(None),
+    }
+}
+
+impl World {
+    /// Builds a world from a GEXF document.
+    pub fn read_gexf(input: &str) -> Result<World, Error> {
+        let mut reader = Reader::from_str(input);
+
+        let mut definitions: HashMap<(String, String), AttributeDefinition> = HashMap::new();
+        let mut nodes: Vec<Pending> = vec![];
+        let mut edges: Vec<Pending> = vec![];
+        let mut directed = true;
+        let mut time_format = "double".to_string();
+
+        let mut owners: Vec<Owner> = vec![];
+        let mut class = "node".to_string();
+        let mut current_definition: Option<(String, String)> = None;
+        let mut in_default = false;
+        let mut text = String::new();
+
+        loop {
+            let position = reader.buffer_position() as usize;
+            let parse_error = |e: quick_xml::Error| Error::Parse {
+                line: line_at(input, position),
+                message: e.to_string(),
+            };
+
+            let event = reader.read_event().map_err(parse_error)?;
+            match event {
+                Event::Start(ref element) | Event::Empty(ref element) => {
+                    let empty = matches!(event, Event::Empty(_));
+                    let owner = match owners.last() {
+                        Some(Owner::Node(i)) => nodes.get_mut(*i),
+                        Some(Owner::Edge(i)) => edges.get_mut(*i),
+                        None => None,
+                    };
+
+                    match element.local_name().as_ref() {
+                        b"graph" => {
+                            directed = attribute(element, "defaultedgetype").map_err(parse_error)?.as_deref() != Some("undirected");
+                            if let Some(format) = attribute(element, "timeformat").map_err(parse_error)? {
+                                time_format = format.to_ascii_lowercase();
+                            }
+                        }
+                        b"attributes" => {
+                            class = attribute(element, "class").map_err(parse_error)?.unwrap_or_else(|| "node".to_string());
+                        }
+                        b"attribute" => {
+                            let id = attribute(element, "id").map_err(parse_error)?.unwrap_or_default();
+                            let title = attribute(element, "title").map_err(parse_error)?.unwrap_or_else(|| id.clone());
+                            let kind = attribute(element, "type").map_err(parse_error)?.unwrap_or_else(|| "string".to_string());
+                            definitions.insert((class.clone(), id.clone()), AttributeDefinition { title, kind, default: None });
+                            if !empty {
+                                current_definition = Some((class.clone(), id));
+                            }
+                        }
+                        b"default" => {
+                            in_default = !empty;
+                            text.clear();
+                        }
+                        b"node" => {
+                            let Some(id) = attribute(element, "id").map_err(parse_error)? else {
+                                return Err(Error::Parse { line: line_at(input, position), message: "node without an id".to_string() });
+                            };
+                            let mut node = Pending {
+                                id,
+                                label: attribute(element, "label").map_err(parse_error)?,
+                                ..Pending::default()
+                            };
+                            let start = attribute(element, "start").map_err(parse_error)?;
+                            let end = attribute(element, "end").map_err(parse_error)?;
+                            if start.is_some() || end.is_some() {
+                                node.spells.push((start, end));
+                            }
+                            nodes.push(node);
+                            if !empty {
+                                owners.push(Owner::Node(nodes.len() - 1));
+                            }
+                        }
+                        b"edge" => {
+                            let source = attribute(element, "source").map_err(parse_error)?;
+                            let target = attribute(element, "target").map_err(parse_error)?;
+                            let (Some(source), Some(target)) = (source, target) else {
+                                return Err(Error::Parse { line: line_at(input, position), message: "edge without a source or target".to_string() });
+                            };
+                            let mut edge = Pending {
+                                id: attribute(element, "id").map_err(parse_error)?.unwrap_or_default(),
+                                label: attribute(element, "label").map_err(parse_error)?,
+                                source,
+                                target,
+                                weight: parse_float(element, "weight").map_err(parse_error)?,
+                                kind: attribute(element, "kind").map_err(parse_error)?,
+                                ..Pending::default()
+                            };
+                            let start = attribute(element, "start").map_err(parse_error)?;
+                            let end = attribute(element, "end").map_err(parse_error)?;
+                            if start.is_some() || end.is_some() {
+                                edge.spells.push((start, end));
+                            }
+                            edges.push(edge);
+                            if !empty {
+                                owners.push(Owner::Edge(edges.len() - 1));
+                            }
+                        }
+                        b"attvalue" => {
+                            if let Some(owner) = owner {
+                                let key = attribute(element, "for").map_err(parse_error)?
+                                    .or(attribute(element, "id").map_err(parse_error)?)
+                                    .unwrap_or_default();
+                                let value = attribute(element, "value").map_err(parse_error)?.unwrap_or_default();
+                                let start = attribute(element, "start").map_err(parse_error)?;
+                                let end = attribute(element, "end").map_err(parse_error)?;
+                                owner.values.push(AttValue { key, value, start, end });
+                            }
+                        }
+                        b"spell" => {
+                            if let Some(owner) = owner {
+                                let start = attribute(element, "start").map_err(parse_error)?;
+                                let end = attribute(element, "end").map_err(parse_error)?;
+                                owner.spells.push((start, end));
+                            }
+                        }
+                        b"color" => {
+                            if let Some(owner) = owner {
+                                owner.viz.color = parse_color(element).map_err(parse_error)?;
+                            }
+                        }
+                        b"position" => {
+                            if let Some(owner) = owner {
+                                let x = parse_float(element, "x").map_err(parse_error)?.unwrap_or(0.);
+                                let y = parse_float(element, "y").map_err(parse_error)?.unwrap_or(0.);
+                                let z = parse_float(element, "z").map_err(parse_error)?.unwrap_or(0.);
+                                owner.viz.position = Some(Vec3::new(x, y, z));
+                            }
+                        }
+                        b"size" => {
+                            if let Some(owner) = owner {
+                                owner.viz.size = parse_float(element, "value").map_err(parse_error)?;
+                            }
+                        }
+                        b"thickness" => {
+                            if let Some(owner) = owner {
+                                owner.viz.thickness = parse_float(element, "value").map_err(parse_error)?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Text(t) if in_default => {
+                    text.push_str(&t.unescape().map_err(parse_error)?);
+                }
+                Event::End(element) => {
+                    match element.local_name().as_ref() {
+                        b"attribute" => current_definition = None,
+                        b"default" => {
+                            if let Some(definition) = current_definition.as_ref().and_then(|d| definitions.get_mut(d)) {
+                                definition.default = Some(text.clone());
+                            }
+                            in_default = false;
+                        }
+                        b"node" | b"edge" => {
+                            owners.pop();
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        let time_value = |text: Option<String>| -> Option<Value> {
+            let text = text?;
+            let value = match time_format.as_str() {
+                "date" | "datetime" => parse_timestamp(&text).map(Value::Timestamp),
+                _ => text.trim().parse::<f64>().ok().map(Value::Number),
+            };
+            Some(value.unwrap_or(Value::String(text)))
+        };
+
+        // Resolves the attribute values of a node or edge, including the defaults, values with
+        // bounds also go into the timeline
+        let properties = |class: &str, values: Vec<AttValue>| -> (Properties, Vec<TimedValue>) {
+            let mut properties = Properties::new();
+            let mut timeline = vec![];
+            for ((c, _), definition) in definitions.iter() {
+                if let (true, Some(default)) = (c == class, &definition.default) {
+                    properties.insert(definition.title.clone(), parse_value(default, &definition.kind));
+                }
+            }
+            for AttValue { key, value, start, end } in values {
+                // Some writers refer to attributes by title instead of by id
+                let definition = definitions.get(&(class.to_string(), key.clone()))
+                    .or_else(|| definitions.iter().find(|((c, _), d)| c == class && d.title == key).map(|(_, d)| d));
+                let (name, value) = match definition {
+                    Some(definition) => (definition.title.clone(), parse_value(&value, &definition.kind)),
+                    None => (key, Value::String(value)),
+                };
+                if start.is_some() || end.is_some() {
+                    let spell = Spell { start: time_value(start), end: time_value(end) };
+                    timeline.push(TimedValue { name: name.clone(), value: value.clone(), spell });
+                }
+                properties.insert(name, value);
+            }
+            (properties, timeline)
+        };
+
+        let mut world = World::empty();
+        world.set_directed(directed);
+
+        let mut ids = HashMap::new();
+        for pending in nodes {
+            let (mut properties, mut timeline) = properties("node", pending.values);
+            // The kind has no timeline
+            timeline.retain(|v| v.name != "type");
+            let kind = match properties.remove("type") {
+                Some(Value::String(kind)) => Some(kind),
+                Some(other) => Some(other.to_string()),
+                None => None,
+            };
+            let attributes = NodeAttributes {
+                id: Some(pending.id.clone()),
+                label: pending.label,
+                kind,
+                properties,
+                timeline,
+                color: pending.viz.color,
+                size: pending.viz.size,
+                spells: pending.spells.into_iter().map(|(start, end)| Spell { start: time_value(start), end: time_value(end) }).collect(),
+                ..NodeAttributes::default()
+            };
+
+            let mut node = Node::new_random(0.);
+            if let Some(position) = pending.viz.position {
+                node.pos = position;
+            }
+
+            if ids.contains_key(&pending.id) {
+                return Err(Error::Invalid(format!("duplicate node id '{}'", pending.id)));
+            }
+            let id = world.add_node_with(node, attributes);
+            ids.insert(pending.id, id);
+        }
+
+        for pending in edges {
+            let (properties, timeline) = properties("edge", pending.values);
+            let attributes = EdgeAttributes {
+                id: Some(pending.id.clone()).filter(|id| !id.is_empty()),
+                relation: pending.kind,
+                label: pending.label,
+                properties,
+                timeline,
+                color: pending.viz.color,
+                thickness: pending.viz.thickness,
+                spells: pending.spells.into_iter().map(|(start, end)| Spell { start: time_value(start), end: time_value(end) }).collect(),
+            };
+
+            let source = *ids.get(&pending.source).ok_or_else(|| Error::Invalid(format!("edge '{}' references unknown node '{}'", pending.id, pending.source)))?;
+            let target = *ids.get(&pending.target).ok_or_else(|| Error::Invalid(format!("edge '{}' references unknown node '{}'", pending.id, pending.target)))?;
+            world.add_edge_with(source, target, pending.weight.unwrap_or(1.), attributes);
+        }
+
+        Ok(world)
+    }
+
+    pub fn load_gexf(path: impl AsRef<Path>) -> Result<World, Error> {
+        Self::read_gexf(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the world as a GEXF 1.3 document. Nodes and edges keep the ids they were read with,
+    /// others are identified by their slot.
+    pub fn write_gexf(&self, writer: &mut impl Write) -> Result<(), Error> {
+        let node_spells = self.nodes().flat_map(|(id, _)| {
+            let attributes = self.attributes(id).unwrap();
+            attributes.spells.iter().chain(attributes.timeline.iter().map(|v| &v.spell))
+        });
+        let edge_spells = self.edges().flat_map(|(id, _)| {
+            let attributes = self.edge_attributes(id).unwrap();
+            attributes.spells.iter().chain(attributes.timeline.iter().map(|v| &v.spell))
+        });
+        let bounds = node_spells.chain(edge_spells)
+            .flat_map(|s| [s.start.as_ref(), s.end.as_ref()])
+            .flatten()
+            .collect::<Vec<_>>();
+
+        // Timestamps are written as dates when possible
+        let dynamic = !bounds.is_empty();
+        let time_format = if !bounds.iter().any(|b| matches!(b, Value::Timestamp(_))) {
+            "double"
+        } else if bounds.iter().all(|b| !format_value(b).contains('T')) {
+            "date"
+        } else {
+            "dateTime"
+        };
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<gexf xmlns="http://gexf.net/1.3" xmlns:viz="http://gexf.net/1.3/viz" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://gexf.net/1.3 http://gexf.net/1.3/gexf.xsd" version="1.3">"#)?;
+
+        let edge_type = if self.is_directed() { "directed" } else { "undirected" };
+        if dynamic {
+            writeln!(writer, r#"  <graph mode="dynamic" defaultedgetype="{}" timeformat="{}">"#, edge_type, time_format)?;
+        } else {
+            writeln!(writer, r#"  <graph mode="static" defaultedgetype="{}">"#, edge_type)?;
+        }
+
+        let mut node_types = attribute_types(self.nodes().flat_map(|(id, _)| {
+            let attributes = self.attributes(id).unwrap();
+            attributes.properties.iter().chain(attributes.timeline.iter().map(|v| (&v.name, &v.value)))
+        }));
+        if self.nodes().any(|(id, _)| self.kind(id).is_some()) {
+            node_types.push(("type".to_string(), "string"));
+        }
+        let edge_types = attribute_types(self.edges().flat_map(|(id, _)| {
+            let attributes = self.edge_attributes(id).unwrap();
+            attributes.properties.iter().chain(attributes.timeline.iter().map(|v| (&v.name, &v.value)))
+        }));
+
+        for (class, types) in [("node", &node_types), ("edge", &edge_types)] {
+            if types.is_empty() {
+                continue;
+            }
+            writeln!(writer, r#"    <attributes class="{}">"#, class)?;
+            for (i, (title, kind)) in types.iter().enumerate() {
+                writeln!(writer, r#"      <attribute id="{}" title="{}" type="{}"/>"#, i, escape(title.as_str()), kind)?;
+            }
+            writeln!(writer, "    </attributes>")?;
+        }
+
+        let spell_bounds = |spell: &Spell| -> String {
+            let mut bounds = String::new();
+            if let Some(start) = &spell.start {
+                bounds += &format!(r#" start="{}""#, escape(format_value(start)));
+            }
+            if let Some(end) = &spell.end {
+                bounds += &format!(r#" end="{}""#, escape(format_value(end)));
+            }
+            bounds
+        };
+
+        let write_details = |writer: &mut dyn Write, values: Vec<(usize, String, Option<Spell>)>, spells: &[Spell], color: Option<Color>| -> Result<(), Error> {
+            if !values.is_empty() {
+                writeln!(writer, "        <attvalues>")?;
+                for (i, value, spell) in values {
+                    let bounds = spell.as_ref().map(spell_bounds).unwrap_or_default();
+                    writeln!(writer, r#"          <attvalue for="{}" value="{}"{}/>"#, i, escape(value), bounds)?;
+                }
+                writeln!(writer, "        </attvalues>")?;
+            }
+            if spells.len() > 1 {
+                writeln!(writer, "        <spells>")?;
+                for spell in spells {
+                    writeln!(writer, "          <spell{}/>", spell_bounds(spell))?;
+                }
+                writeln!(writer, "        </spells>")?;
+            }
+            if let Some(c) = color {
+                writeln!(writer, r#"        <viz:color r="{}" g="{}" b="{}" a="{}"/>"#, c.r, c.g, c.b, c.a)?;
+            }
+            Ok(())
+        };
+
+        // A single spell is written as the start and end of the element itself
+        let element_bounds = |spells: &[Spell]| -> String {
+            match spells {
+                [spell] => spell_bounds(spell),
+                _ => String::new(),
+            }
+        };
+
+        let node_ids = element_ids(self.nodes().map(|(id, _)| self.attributes(id).unwrap().id.as_deref()), "");
+        let edge_ids = element_ids(self.edges().map(|(id, _)| self.edge_attributes(id).unwrap().id.as_deref()), "");
+
+        writeln!(writer, "    <nodes>")?;
+        for (slot, (id, node)) in self.nodes().enumerate() {
+            let attributes = self.attributes(id).unwrap();
+            let label = attributes.label.as_ref().map(|l| format!(r#" label="{}""#, escape(l.as_str()))).unwrap_or_default();
+            writeln!(writer, r#"      <node id="{}"{}{}>"#, escape(node_ids[slot].as_str()), label, element_bounds(&attributes.spells))?;
+
+            let mut values = attribute_values(&node_types, &attributes.properties, &attributes.timeline);
+            if let Some(kind) = &attributes.kind {
+                values.push((node_types.len() - 1, kind.clone(), None));
+            }
+            write_details(writer, values, &attributes.spells, attributes.color)?;
+
+            writeln!(writer, r#"        <viz:position x="{}" y="{}" z="{}"/>"#, node.pos.x, node.pos.y, node.pos.z)?;
+            if let Some(size) = attributes.size {
+                writeln!(writer, r#"        <viz:size value="{}"/>"#, size)?;
+            }
+            writeln!(writer, "      </node>")?;
+        }
+        writeln!(writer, "    </nodes>")?;
+
+        writeln!(writer, "    <edges>")?;
+        for (slot, (id, edge)) in self.edges().enumerate() {
+            let attributes = self.edge_attributes(id).unwrap();
+            let source = &node_ids[self.node_slot(edge.source).unwrap()];
+            let target = &node_ids[self.node_slot(edge.target).unwrap()];
+            let mut optional = String::new();
+            if let Some(relation) = &attributes.relation {
+                optional += &format!(r#" kind="{}""#, escape(relation.as_str()));
+            }
+            if let Some(label) = &attributes.label {
+                optional += &format!(r#" label="{}""#, escape(label.as_str()));
+            }
+            writeln!(
+                writer,
+                r#"      <edge id="{}" source="{}" target="{}" weight="{}"{}{}>"#,
+                escape(edge_ids[slot].as_str()), escape(source.as_str()), escape(target.as_str()), edge.weight, optional, element_bounds(&attributes.spells)
+            )?;
+
+            let values = attribute_values(&edge_types, &attributes.properties, &attributes.timeline);
+            write_details(writer, values, &attributes.spells, attributes.color)?;
+
+            if let Some(thickness) = attributes.thickness {
+                writeln!(writer, r#"        <viz:thickness value="{}"/>"#, thickness)?;
+            }
+            writeln!(writer, "      </edge>")?;
+        }
+        writeln!(writer, "    </edges>")?;
+
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</gexf>")?;
+        Ok(())
+    }
+
+    pub fn save_gexf(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_gexf(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+//! [GraphML](http://graphml.graphdrawing.org/) reader and writer.
+//!
+//! Node and edge `data` elements are mapped onto properties, typed by the `attr.type` of their
+//! key. A few key names have a fixed meaning:
+//!
+//! - nodes: `label`, `type` (the node kind) and `x`, `y`, `z` (the position)
+//! - edges: `label`, `relation` and `weight`
+//!
+//! Node and edge ids are kept and written back, nodes without one are numbered by slot.
+//! GraphML has no list or timestamp type, lists are written as strings and timestamps as longs.
+
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use glam::Vec3;
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use crate::attributes::{EdgeAttributes, NodeAttributes, Properties, Value};
+use crate::io::{attribute, element_ids, line_at, Error};
+use crate::world::{Node, World};
+
+struct Key {
+    name: String,
+    domain: String,
+    kind: String,
+    default: Option<String>,
+}
+
+struct PendingNode {
+    id: String,
+    data: HashMap<String, String>,
+}
+
+struct PendingEdge {
+    id: Option<String>,
+    source: String,
+    target: String,
+    data: HashMap<String, String>,
+}
+
+enum Owner {
+    Node(usize),
+    Edge(usize),
+    Other,
+}
+
+fn parse_value(text: &str, kind: &str) -> Value {
+    match kind {
+        "boolean" => match text.trim() {
+            "true" | "1" => Value::Bool(true),
+            "false" | "0" => Value::Bool(false),
+            _ => Value::String(text.to_string()),
+        },
+        "int" | "long" | "float" | "double" => text.trim().parse::<f64>()
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(text.to_string())),
+        _ => Value::String(text.to_string()),
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "double",
+        Value::Bool(_) => "boolean",
+        Value::Timestamp(_) => "long",
+        Value::String(_) | Value::List(_) => "string",
+    }
+}
+
+/// Collects the GraphML key of every property name, the type is taken from the first value and
+/// falls back to a string when the values disagree.
+fn property_keys<'a>(properties: impl Iterator<Item = &'a Properties>) -> Vec<(String, &'static str)> {
+    let mut keys: Vec<(String, &'static str)> = vec![];
+    for properties in properties {
+        for (name, value) in properties.iter() {
+            match keys.iter_mut().find(|(n, _)| n == name) {
+                Some((_, kind)) => {
+                    if *kind != value_type(value) {
+                        *kind = "string";
+                    }
+                }
+                None => keys.push((name.clone(), value_type(value))),
+            }
+        }
+    }
+    keys.sort();
+    keys
+}
+
+impl World {
+    /// Builds a world from a GraphML document.
+    pub fn read_graphml(input: &str) -> Result<World, Error> {
+        let mut reader = Reader::from_str(input);
+
+        let mut keys: HashMap<String, Key> = HashMap::new();
+        let mut nodes: Vec<PendingNode> = vec![];
+        let mut edges: Vec<PendingEdge> = vec![];
+        let mut directed = None;
+
+        // Elements that data can be attached to, the innermost one is last
+        let mut owners: Vec<Owner> = vec![];
+        let mut current_key: Option<String> = None;
+        let mut data_key: Option<String> = None;
+        let mut in_default = false;
+        let mut text = String::new();
+
+        loop {
+            let position = reader.buffer_position() as usize;
+            let parse_error = |e: quick_xml::Error| Error::Parse {
+                line: line_at(input, position),
+                message: e.to_string(),
+            };
+
+            let event = reader.read_event().map_err(parse_error)?;
+            match event {
+                Event::Start(ref element) | Event::Empty(ref element) => {
+                    let empty = matches!(event, Event::Empty(_));
+                    match element.local_name().as_ref() {
+                        b"key" => {
+                            let id = attribute(element, "id").map_err(parse_error)?.unwrap_or_default();
+                            let name = attribute(element, "attr.name").map_err(parse_error)?.unwrap_or_else(|| id.clone());
+                            keys.insert(id.clone(), Key {
+                                name,
+                                domain: attribute(element, "for").map_err(parse_error)?.unwrap_or_else(|| "all".to_string()),
+                                kind: attribute(element, "attr.type").map_err(parse_error)?.unwrap_or_else(|| "string".to_string()),
+                                default: None,
+                            });
+                            if !empty {
+                                current_key = Some(id);
+                            }
+                        }
+                        b"default" => {
+                            in_default = !empty;
+                            text.clear();
+                        }
+                        // Nested graphs are flattened, the outermost graph decides the direction
+                        b"graph" if directed.is_none() => {
+                            let edge_default = attribute(element, "edgedefault").map_err(parse_error)?;
+                            directed = Some(edge_default.as_deref() != Some("undirected"));
+                        }
+                        b"node" => {
+                            let Some(id) = attribute(element, "id").map_err(parse_error)? else {
+                                return Err(Error::Parse { line: line_at(input, position), message: "node without an id".to_string() });
+                            };
+                            nodes.push(PendingNode { id, data: HashMap::new() });
+                            if !empty {
+                                owners.push(Owner::Node(nodes.len() - 1));
+                            }
+                        }
+                        b"edge" => {
+                            let source = attribute(element, "source").map_err(parse_error)?;
+                            let target = attribute(element, "target").map_err(parse_error)?;
+                            let (Some(source), Some(target)) = (source, target) else {
+                                return Err(Error::Parse { line: line_at(input, position), message: "edge without a source or target".to_string() });
+                            };
+                            let id = attribute(element, "id").map_err(parse_error)?;
+                            edges.push(PendingEdge { id, source, target, data: HashMap::new() });
+                            if !empty {
+                                owners.push(Owner::Edge(edges.len() - 1));
+                            }
+                        }
+                        b"hyperedge" if !empty => owners.push(Owner::Other),
+                        b"data" => {
+                            data_key = attribute(element, "key").map_err(parse_error)?;
+                            text.clear();
+                            if empty {
+                                data_key = None;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Text(t) if data_key.is_some() || in_default => {
+                    text.push_str(&t.unescape().map_err(parse_error)?);
+                }
+                Event::CData(t) if data_key.is_some() || in_default => {
+                    text.push_str(&String::from_utf8_lossy(&t));
+                }
+                Event::End(element) => {
+                    match element.local_name().as_ref() {
+                        b"key" => current_key = None,
+                        b"default" => {
+                            if let Some(key) = current_key.as_ref().and_then(|k| keys.get_mut(k)) {
+                                key.default = Some(text.clone());
+                            }
+                            in_default = false;
+                        }
+                        b"node" | b"edge" | b"hyperedge" => {
+                            owners.pop();
+                        }
+                        b"data" => {
+                            if let Some(key) = data_key.take() {
+                                match owners.last() {
+                                    Some(Owner::Node(i)) => { nodes[*i].data.insert(key, text.clone()); }
+                                    Some(Owner::Edge(i)) => { edges[*i].data.insert(key, text.clone()); }
+                                    _ => {}
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        // Fill in the defaults of keys that weren't set explicitly
+        let with_defaults = |data: &mut HashMap<String, String>, domain: &str| {
+            for (id, key) in keys.iter() {
+                if key.domain != domain && key.domain != "all" {
+                    continue;
+                }
+                if let Some(default) = &key.default {
+                    data.entry(id.clone()).or_insert_with(|| default.clone());
+                }
+            }
+        };
+
+        let mut world = World::empty();
+        world.set_directed(directed.unwrap_or(true));
+
+        let mut ids = HashMap::new();
+        for mut pending in nodes {
+            with_defaults(&mut pending.data, "node");
+
+            let mut node = Node::new_random(0.);
+            let mut position = [None; 3];
+            let mut attributes = NodeAttributes { id: Some(pending.id.clone()), ..NodeAttributes::default() };
+            for (id, text) in pending.data {
+                let Some(key) = keys.get(&id) else {
+                    return Err(Error::Invalid(format!("node '{}' uses undeclared key '{}'", pending.id, id)));
+                };
+                match key.name.as_str() {
+                    "label" => attributes.label = Some(text),
+                    "type" => attributes.kind = Some(text),
+                    "x" | "y" | "z" if text.trim().parse::<f32>().is_ok() => {
+                        let axis = (key.name.as_bytes()[0] - b'x') as usize;
+                        position[axis] = text.trim().parse::<f32>().ok();
+                    }
+                    _ => { attributes.properties.insert(key.name.clone(), parse_value(&text, &key.kind)); }
+                }
+            }
+            if let [Some(x), Some(y), z] = position {
+                node.pos = Vec3::new(x, y, z.unwrap_or(0.));
+            }
+
+            if ids.contains_key(&pending.id) {
+                return Err(Error::Invalid(format!("duplicate node id '{}'", pending.id)));
+            }
+            let id = world.add_node_with(node, attributes);
+            ids.insert(pending.id, id);
+        }
+
+        for mut pending in edges {
+            with_defaults(&mut pending.data, "edge");
+
+            let mut weight = 1.0;
+            let mut attributes = EdgeAttributes { id: pending.id, ..EdgeAttributes::default() };
+            for (id, text) in pending.data {
+                let Some(key) = keys.get(&id) else {
+                    return Err(Error::Invalid(format!("edge '{}' -> '{}' uses undeclared key '{}'", pending.source, pending.target, id)));
+                };
+                match key.name.as_str() {
+                    "label" => attributes.label = Some(text),
+                    "relation" => attributes.relation = Some(text),
+                    "weight" if text.trim().parse::<f32>().is_ok() => weight = text.trim().parse::<f32>().unwrap(),
+                    _ => { attributes.properties.insert(key.name.clone(), parse_value(&text, &key.kind)); }
+                }
+            }
+
+            let source = *ids.get(&pending.source).ok_or_else(|| Error::Invalid(format!("edge references unknown node '{}'", pending.source)))?;
+            let target = *ids.get(&pending.target).ok_or_else(|| Error::Invalid(format!("edge references unknown node '{}'", pending.target)))?;
+            world.add_edge_with(source, target, weight, attributes);
+        }
+
+        Ok(world)
+    }
+
+    pub fn load_graphml(path: impl AsRef<Path>) -> Result<World, Error> {
+        Self::read_graphml(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the world as a GraphML document. Nodes and edges keep the ids they were read with,
+    /// other nodes are identified by their slot.
+    pub fn write_graphml(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd">"#)?;
+
+        let node_keys = property_keys(self.nodes().map(|(id, _)| self.properties(id).unwrap()));
+        let edge_keys = property_keys(self.edges().map(|(id, _)| &self.edge_attributes(id).unwrap().properties));
+
+        let mut key_id = 0;
+        let mut declare = |writer: &mut dyn Write, domain: &str, name: &str, kind: &str| -> Result<String, Error> {
+            let id = format!("d{}", key_id);
+            key_id += 1;
+            writeln!(writer, r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#, id, domain, escape(name), kind)?;
+            Ok(id)
+        };
+
+        let label_key = declare(writer, "node", "label", "string")?;
+        let kind_key = declare(writer, "node", "type", "string")?;
+        let position_keys = [
+            declare(writer, "node", "x", "float")?,
+            declare(writer, "node", "y", "float")?,
+            declare(writer, "node", "z", "float")?,
+        ];
+        let node_property_keys = node_keys.iter()
+            .map(|(name, kind)| Ok((name.clone(), declare(writer, "node", name, kind)?)))
+            .collect::<Result<HashMap<_, _>, Error>>()?;
+
+        let edge_label_key = declare(writer, "edge", "label", "string")?;
+        let relation_key = declare(writer, "edge", "relation", "string")?;
+        let weight_key = declare(writer, "edge", "weight", "double")?;
+        let edge_property_keys = edge_keys.iter()
+            .map(|(name, kind)| Ok((name.clone(), declare(writer, "edge", name, kind)?)))
+            .collect::<Result<HashMap<_, _>, Error>>()?;
+
+        let edge_default = if self.is_directed() { "directed" } else { "undirected" };
+        writeln!(writer, r#"  <graph id="G" edgedefault="{}">"#, edge_default)?;
+
+        let data = |writer: &mut dyn Write, key: &str, value: &str| -> Result<(), Error> {
+            writeln!(writer, r#"      <data key="{}">{}</data>"#, key, escape(value))?;
+            Ok(())
+        };
+
+        let node_ids = element_ids(self.nodes().map(|(id, _)| self.attributes(id).unwrap().id.as_deref()), "n");
+        let edge_ids = element_ids(self.edges().map(|(id, _)| self.edge_attributes(id).unwrap().id.as_deref()), "e");
+
+        for (slot, (id, node)) in self.nodes().enumerate() {
+            writeln!(writer, r#"    <node id="{}">"#, escape(node_ids[slot].as_str()))?;
+            let attributes = self.attributes(id).unwrap();
+            if let Some(label) = &attributes.label {
+                data(writer, &label_key, label)?;
+            }
+            if let Some(kind) = &attributes.kind {
+                data(writer, &kind_key, kind)?;
+            }
+            for (key, value) in position_keys.iter().zip(node.pos.to_array()) {
+                data(writer, key, &value.to_string())?;
+            }
+            for (name, value) in attributes.properties.iter() {
+                data(writer, &node_property_keys[name], &value.to_string())?;
+            }
+            writeln!(writer, "    </node>")?;
+        }
+
+        for (slot, (id, edge)) in self.edges().enumerate() {
+            let source = &node_ids[self.node_slot(edge.source).unwrap()];
+            let target = &node_ids[self.node_slot(edge.target).unwrap()];
+            // Edge ids are optional in GraphML, so only the kept ones are written
+            let attributes = self.edge_attributes(id).unwrap();
+            let edge_id = attributes.id.as_ref().map(|_| format!(r#" id="{}""#, escape(edge_ids[slot].as_str()))).unwrap_or_default();
+            writeln!(writer, r#"    <edge{} source="{}" target="{}">"#, edge_id, escape(source.as_str()), escape(target.as_str()))?;
+            if let Some(label) = &attributes.label {
+                data(writer, &edge_label_key, label)?;
+            }
+            if let Some(relation) = &attributes.relation {
+                data(writer, &relation_key, relation)?;
+            }
+            data(writer, &weight_key, &edge.weight.to_string())?;
+            for (name, value) in attributes.properties.iter() {
+                data(writer, &edge_property_keys[name], &value.to_string())?;
+            }
+            writeln!(writer, "    </edge>")?;
+        }
+
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        Ok(())
+    }
+
+    pub fn save_graphml(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_graphml(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! Reading and writing graphs in external file formats.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use quick_xml::events::BytesStart;
+use crate::io::csv::CsvOptions;
+use crate::world::World;
+
+pub mod csv;
+pub mod dot;
+pub mod gexf;
+pub mod graphml;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    /// The input is malformed, `line` is 1-based.
+    Parse { line: usize, message: String },
+    /// The input is well-formed but does not describe a valid graph.
+    Invalid(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            Error::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::Io(value)
+    }
+}
+
+impl World {
+    /// Reads a graph in the format given by the extension of the path, GraphML, GEXF, DOT or a
+    /// CSV or TSV edge list with the default columns.
+    pub fn load(path: impl AsRef<Path>) -> Result<World, Error> {
+        let path = path.as_ref();
+        match extension(path).as_str() {
+            "graphml" => Self::load_graphml(path),
+            "gexf" => Self::load_gexf(path),
+            "dot" | "gv" => Self::load_dot(path),
+            "csv" => Self::load_csv(None, path, &CsvOptions::default()),
+            "tsv" => Self::load_csv(None, path, &CsvOptions::tsv()),
+            _ => Err(Error::Invalid(format!("cannot read {}, unknown file extension", path.display()))),
+        }
+    }
+
+    /// Writes the world in the format given by the extension of the path, GraphML, GEXF or DOT.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        match extension(path).as_str() {
+            "graphml" => self.save_graphml(path),
+            "gexf" => self.save_gexf(path),
+            "dot" | "gv" => self.save_dot(path),
+            _ => Err(Error::Invalid(format!("cannot write {}, unknown file extension", path.display()))),
+        }
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+/// Returns the 1-based line number of a byte offset into the input.
+pub(crate) fn line_at(input: &str, offset: usize) -> usize {
+    input.as_bytes()[..offset.min(input.len())].iter().filter(|b| **b == b'\n').count() + 1
+}
+
+/// The ids nodes or edges were read with where they are unique, the others are numbered by slot
+/// after the prefix while avoiding the ids that are kept.
+pub(crate) fn element_ids<'a>(stored: impl Iterator<Item = Option<&'a str>>, prefix: &str) -> Vec<String> {
+    let stored = stored.collect::<Vec<_>>();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for id in stored.iter().flatten() {
+        *counts.entry(id).or_default() += 1;
+    }
+
+    let mut used = counts.iter().filter(|(_, count)| **count == 1).map(|(id, _)| id.to_string()).collect::<HashSet<_>>();
+    stored.iter().enumerate()
+        .map(|(slot, id)| match id {
+            Some(id) if counts[id] == 1 => id.to_string(),
+            _ => {
+                let mut id = format!("{}{}", prefix, slot);
+                let mut suffix = 0;
+                while used.contains(&id) {
+                    suffix += 1;
+                    id = format!("{}{}-{}", prefix, slot, suffix);
+                }
+                used.insert(id.clone());
+                id
+            }
+        })
+        .collect()
+}
+
+/// Returns the unescaped value of an XML attribute.
+pub(crate) fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, quick_xml::Error> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.as_ref() == name.as_bytes() {
+            return Ok(Some(attribute.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+// Conversions between days since the unix epoch and the proleptic Gregorian calendar, see
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Parses an ISO 8601 date (`2024-03-01`) or date-time (`2024-03-01T12:30:00.5+01:00`) into
+/// milliseconds since the unix epoch. Date-times without an offset are taken as UTC.
+pub(crate) fn parse_timestamp(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+
+    let mut fields = date.split('-');
+    let year = fields.next()?.parse::<i64>().ok()?;
+    let month = fields.next()?.parse::<i64>().ok()?;
+    let day = fields.next()?.parse::<i64>().ok()?;
+    if fields.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut millis = days_from_civil(year, month, day) * 86_400_000;
+
+    if let Some(time) = time {
+        let (time, offset) = if let Some(time) = time.strip_suffix('Z') {
+            (time, 0)
+        } else if let Some(i) = time.rfind(['+', '-']) {
+            let (hours, minutes) = time[i + 1..].split_once(':').unwrap_or((&time[i + 1..], "0"));
+            let offset = hours.parse::<i64>().ok()? * 60 + minutes.parse::<i64>().ok()?;
+            (&time[..i], if time.as_bytes()[i] == b'-' { -offset } else { offset })
+        } else {
+            (time, 0)
+        };
+
+        let mut fields = time.split(':');
+        let hours = fields.next()?.parse::<i64>().ok()?;
+        let minutes = fields.next().map(|m| m.parse::<i64>()).unwrap_or(Ok(0)).ok()?;
+        let seconds = fields.next().map(|s| s.parse::<f64>()).unwrap_or(Ok(0.)).ok()?;
+        millis += (hours * 60 + minutes - offset) * 60_000 + (seconds * 1000.).round() as i64;
+    }
+
+    Some(millis)
+}
+
+/// Formats milliseconds since the unix epoch as an ISO 8601 date, or as a UTC date-time when the
+/// timestamp doesn't fall on midnight.
+pub(crate) fn format_timestamp(millis: i64) -> String {
+    let days = millis.div_euclid(86_400_000);
+    let time = millis.rem_euclid(86_400_000);
+    let (year, month, day) = civil_from_days(days);
+    if time == 0 {
+        return format!("{:04}-{:02}-{:02}", year, month, day);
+    }
+
+    let (hours, minutes, seconds, millis) = (time / 3_600_000, time / 60_000 % 60, time / 1000 % 60, time % 1000);
+    if millis == 0 {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hours, minutes, seconds)
+    } else {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, hours, minutes, seconds, millis)
+    }
+}
@@ -0,0 +1,267 @@
+//! Layout engines that move the nodes of a world with the force model of the physics shaders.
+//!
+//! [`PhysicsComponent`](crate::PhysicsComponent) runs the model in Vulkan compute shaders,
+//! [`CpuLayout`] runs the same steps on the CPU with rayon. The CPU engine needs no GPU, so
+//! layouts can be computed headless and the results of the shaders can be checked against it.
+
+use std::collections::HashMap;
+use glam::{IVec3, Vec3};
+use rayon::prelude::*;
+use crate::barnes_hut;
+use crate::barnes_hut::REPULSION_SCALE;
+use crate::gpu_physics::{PhysicsParameters, RepulsionMode, StepEnergy};
+use crate::world::{Changes, World};
+
+/// Scale of the edge springs, as used by `physics_edges.comp`.
+const EDGE_SCALE: f32 = 0.1;
+
+/// Springs shorter than this do not pull, to avoid normalizing a zero vector.
+const MIN_EDGE_LENGTH: f32 = 0.001;
+
+/// Something that moves the nodes of a world, see [`CpuLayout`] and
+/// [`PhysicsComponent`](crate::PhysicsComponent).
+pub trait LayoutEngine {
+    /// What the engine runs on, the renderer for the GPU and nothing for the CPU.
+    type Context;
+
+    /// Takes over the edits made to the world since the last sync.
+    fn sync(&mut self, context: &mut Self::Context, world: &mut World);
+
+    /// Runs the given amount of simulation steps.
+    fn run(&mut self, context: &mut Self::Context, steps: usize);
+
+    /// Copies the simulated positions and velocities back into the world.
+    fn read_positions(&mut self, context: &mut Self::Context, world: &mut World);
+
+    /// Motion of the nodes during the most recent step that was measured.
+    fn energy(&self) -> Option<StepEnergy>;
+
+    fn parameters(&mut self) -> &mut PhysicsParameters;
+}
+
+/// The force model of `physics_edges.comp` and `physics.comp` on the CPU.
+pub struct CpuLayout {
+    pub parameters: PhysicsParameters,
+    /// Scales the longest step, cools down over time and is reset by editing the world.
+    pub temperature: f32,
+    positions: Vec<Vec3>,
+    velocities: Vec<Vec3>,
+    pinned: Vec<bool>,
+    /// Start of the springs of every node slot in `springs`, with one extra entry at the end.
+    offsets: Vec<usize>,
+    /// Other end and weight of every spring, both directions of an edge are stored.
+    springs: Vec<(usize, f32)>,
+    energy: Option<StepEnergy>,
+}
+
+impl CpuLayout {
+    /// Starts from the current layout of the world, taking over its pending edits.
+    pub fn new(world: &mut World) -> Self {
+        let mut layout = Self {
+            parameters: PhysicsParameters::default(),
+            temperature: 1.,
+            positions: vec![],
+            velocities: vec![],
+            pinned: vec![],
+            offsets: vec![0],
+            springs: vec![],
+            energy: None,
+        };
+        world.take_changes();
+        layout.apply_changes(world, &Changes::everything(world));
+        layout
+    }
+
+    /// Simulated positions, by node slot.
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    /// Lets the nodes move at full speed again.
+    pub fn reheat(&mut self) {
+        self.temperature = 1.;
+    }
+
+    fn apply_changes(&mut self, world: &World, changes: &Changes) {
+        // Nodes added and removed again since the last sync were moved out of slots we never saw
+        let count = world.node_count();
+        let required = changes.moved.iter().map(|(from, _)| from + 1).max().unwrap_or(0).max(count);
+        if self.positions.len() < required {
+            self.positions.resize(required, Vec3::ZERO);
+            self.velocities.resize(required, Vec3::ZERO);
+        }
+
+        for (from, to) in changes.moved.iter() {
+            self.positions[*to] = self.positions[*from];
+            self.velocities[*to] = self.velocities[*from];
+        }
+        self.positions.truncate(count);
+        self.velocities.truncate(count);
+
+        for slot in changes.nodes.iter() {
+            let node = world.node(world.node_at_slot(*slot).unwrap()).unwrap();
+            self.positions[*slot] = node.pos;
+            self.velocities[*slot] = node.vel;
+        }
+        self.pinned = world.nodes().map(|(_, node)| node.pinned).collect();
+
+        // Added nodes have no springs yet, but need their entry in the offsets
+        if !changes.edges && self.offsets.len() == count + 1 {
+            return;
+        }
+
+        let mut springs = world.edges()
+            .flat_map(|(_, edge)| {
+                let source = world.node_slot(edge.source).unwrap();
+                let target = world.node_slot(edge.target).unwrap();
+                [(source, target, edge.weight), (target, source, edge.weight)]
+            })
+            .collect::<Vec<_>>();
+        springs.sort_by_key(|(node, _, _)| *node);
+
+        self.offsets = vec![0; count + 1];
+        for (node, _, _) in springs.iter() {
+            self.offsets[node + 1] += 1;
+        }
+        for i in 0..count {
+            self.offsets[i + 1] += self.offsets[i];
+        }
+        self.springs = springs.into_iter().map(|(_, other, weight)| (other, weight)).collect();
+    }
+
+    /// Average pull of the springs of a node.
+    fn spring_force(&self, node: usize) -> Vec3 {
+        let springs = &self.springs[self.offsets[node]..self.offsets[node + 1]];
+        if springs.is_empty() {
+            return Vec3::ZERO;
+        }
+
+        let p = self.positions[node];
+        let force = springs.iter()
+            .map(|(other, weight)| {
+                let diff = p - self.positions[*other];
+                if diff.length() > MIN_EDGE_LENGTH {
+                    -diff * EDGE_SCALE * self.parameters.edge_attraction * weight
+                } else {
+                    Vec3::ZERO
+                }
+            })
+            .sum::<Vec3>();
+        force / springs.len() as f32
+    }
+
+    /// Runs one simulation step, the spring pass and then the node pass.
+    pub fn step(&mut self) {
+        let parameters = self.parameters;
+
+        // The springs only change the velocity, like physics_edges.comp
+        let velocities = (0..self.positions.len()).into_par_iter()
+            .map(|i| {
+                let mut velocity = self.velocities[i] * (1. - parameters.damping);
+                let force = self.spring_force(i);
+                if force.length() < 1. && !self.pinned[i] {
+                    velocity += force / parameters.mass * parameters.time_step;
+                }
+                velocity
+            })
+            .collect::<Vec<Vec3>>();
+
+        let repulsion = match parameters.repulsion_mode {
+            RepulsionMode::AllPairs => barnes_hut::repulsion_exact(&self.positions, parameters.repulsion),
+            RepulsionMode::BarnesHut => barnes_hut::repulsion(&self.positions, parameters.repulsion, parameters.theta),
+            RepulsionMode::SpatialHash => repulsion_within(&self.positions, parameters.repulsion, parameters.cutoff),
+        };
+
+        // Semi-implicit Euler with the step clamped by the temperature, like physics.comp
+        let max_step = parameters.max_displacement * self.temperature;
+        let (positions, velocities): (Vec<Vec3>, Vec<Vec3>) = (0..self.positions.len()).into_par_iter()
+            .map(|i| {
+                let p = self.positions[i];
+                if self.pinned[i] {
+                    return (p, Vec3::ZERO);
+                }
+
+                let force = repulsion[i] - p * parameters.center_attraction;
+                let mut velocity = velocities[i] + force / parameters.mass * parameters.time_step;
+                let mut step = velocity * parameters.time_step;
+                let distance = step.length();
+                if distance > max_step {
+                    step *= max_step / distance;
+                    velocity *= max_step / distance;
+                }
+                (p + step, velocity)
+            })
+            .unzip();
+
+        let energy = positions.iter().zip(&self.positions).zip(&velocities)
+            .fold(StepEnergy::default(), |e, ((p, previous), v)| StepEnergy {
+                kinetic_energy: e.kinetic_energy + 0.5 * parameters.mass * v.length_squared(),
+                max_displacement: e.max_displacement.max(p.distance(*previous)),
+            });
+
+        self.positions = positions;
+        self.velocities = velocities;
+        self.energy = Some(energy);
+        self.temperature *= parameters.cooling;
+    }
+}
+
+impl LayoutEngine for CpuLayout {
+    type Context = ();
+
+    fn sync(&mut self, _: &mut (), world: &mut World) {
+        let changes = world.take_changes();
+        if !changes.moved.is_empty() || !changes.nodes.is_empty() || changes.edges || changes.pinned {
+            self.reheat();
+        }
+        if !changes.is_empty() {
+            self.apply_changes(world, &changes);
+        }
+    }
+
+    fn run(&mut self, _: &mut (), steps: usize) {
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    fn read_positions(&mut self, _: &mut (), world: &mut World) {
+        world.apply_layout(self.positions.iter().copied().zip(self.velocities.iter().copied()));
+    }
+
+    fn energy(&self) -> Option<StepEnergy> {
+        self.energy
+    }
+
+    fn parameters(&mut self) -> &mut PhysicsParameters {
+        &mut self.parameters
+    }
+}
+
+/// Exact repulsion between the nodes closer than `cutoff`, found through a grid with cells of
+/// that size.
+pub fn repulsion_within(positions: &[Vec3], repulsion: f32, cutoff: f32) -> Vec<Vec3> {
+    let cell = |p: Vec3| (p / cutoff).floor().as_ivec3();
+    let mut grid: HashMap<IVec3, Vec<usize>> = HashMap::new();
+    for (i, p) in positions.iter().enumerate() {
+        grid.entry(cell(*p)).or_default().push(i);
+    }
+
+    let strength = REPULSION_SCALE * repulsion * repulsion;
+    positions.par_iter()
+        .map(|p| {
+            let c = cell(*p);
+            let mut force = Vec3::ZERO;
+            for i in 0..27 {
+                let neighbour = c + IVec3::new(i % 3, (i / 3) % 3, i / 9) - 1;
+                for j in grid.get(&neighbour).into_iter().flatten() {
+                    let q = positions[*j];
+                    if p.distance_squared(q) < cutoff * cutoff {
+                        force += barnes_hut::pair(*p, q, strength);
+                    }
+                }
+            }
+            force
+        })
+        .collect()
+}
@@ -0,0 +1,30 @@
+//! Mycelia is a graph renderer and playground.
+//!
+//! The crate exposes the graph model ([`World`], [`Node`]), the GPU layout engine
+//! ([`PhysicsComponent`]) with its CPU reference ([`CpuLayout`]) and the compute based renderer
+//! ([`GraphRenderer`]). The viewer binary
+//! is a thin egui application built on top of this public API.
+
+pub mod attributes;
+pub mod world;
+pub mod selection;
+pub mod renderer;
+pub mod gpu_physics;
+pub mod barnes_hut;
+pub mod layout;
+pub mod raster;
+pub mod vector;
+pub mod recording;
+pub mod io;
+pub mod project;
+
+pub use attributes::{Color, EdgeAttributes, NodeAttributes, Properties, Spell, TimedValue, Value};
+pub use world::{Edge, EdgeId, Node, NodeId, World};
+pub use selection::{Selection, SelectionMode};
+pub use gpu_physics::{PhysicsComponent, PhysicsParameters, RepulsionMode, StepEnergy};
+pub use renderer::{GraphRenderer, Picked, MAX_PICK_IDS};
+pub use layout::{CpuLayout, LayoutEngine};
+pub use raster::{rasterize, RgbaImage};
+pub use vector::{vectorize, VectorImage};
+pub use recording::Recorder;
+pub use project::{Camera, ProjectSettings};
@@ -1,3 +1,5 @@
+mod cli;
+
 use std::ops::{Mul, RangeInclusive};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -12,15 +14,48 @@ use dotenv::dotenv;
 use egui::{Align2, Checkbox, Slider, TextWrapMode, Vec2};
 use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
 use ordered_float::OrderedFloat;
-use petgraph::visit::NodeCount;
 use rand::random;
-use world::World;
-use crate::gpu_physics::PhysicsComponent;
-use crate::renderer::{GraphRenderer, RenderNode};
+use mycelia::{vectorize, Camera, EdgeId, GraphRenderer, Node, MAX_PICK_IDS, NodeId, Picked, PhysicsComponent, ProjectSettings, Recorder, RepulsionMode, SelectionMode, World};
+
+/// What dragging with the primary button does, clicking always picks a node or edge.
+#[derive(Copy, Clone, PartialEq)]
+enum Tool {
+    Rotate,
+    /// Moves the grabbed node in the camera plane.
+    Move,
+    Box,
+    Lasso,
+}
+
+/// What a click does with the node or edge under the cursor.
+#[derive(Copy, Clone)]
+enum PickAction {
+    Select(SelectionMode),
+    Grab,
+}
+
+/// Node being dragged with the move tool, it is pinned until it is released.
+struct Grab {
+    id: NodeId,
+    was_pinned: bool,
+}
+
+impl Grab {
+    fn new(world: &mut World, id: NodeId) -> Grab {
+        let grab = Grab { id, was_pinned: world.is_pinned(id) };
+        world.set_pinned(id, true);
+        grab
+    }
+
+    fn release(self, world: &mut World) {
+        world.set_pinned(self.id, self.was_pinned);
+    }
+}
 
-mod world;
-mod renderer;
-mod gpu_physics;
+enum ProjectRequest {
+    Save,
+    Open,
+}
 
 struct Application {
     physics_components: PhysicsComponent,
@@ -28,13 +63,42 @@ struct Application {
     world: Arc<Mutex<World>>,
     view_transform: Mat4,
     screen_transform_ortho: Mat4,
-    screen_transform: Mat4,
     camera_dist: f32,
     transform_pers: Mat4,
     perspective_camera: bool,
     step_speed: u32,
     frame: usize,
     auto_rotate: bool,
+    tool: Tool,
+    /// Pointer positions of the current drag, empty when not dragging.
+    drag_path: Vec<egui::Pos2>,
+    shrink_buffers: bool,
+    relation: String,
+    project_path: String,
+    project_request: Option<ProjectRequest>,
+    project_status: String,
+    screenshot_path: String,
+    /// Resolution of screenshots, independent of the window.
+    screenshot_size: [u32; 2],
+    screenshot_requested: bool,
+    /// SVG or PDF file the view is exported to, at the screenshot size.
+    vector_path: String,
+    vector_requested: bool,
+    screenshot_status: String,
+    /// Frames are captured while rendering, at the screenshot size.
+    recording: Option<Recorder>,
+    recording_toggled: bool,
+    recording_directory: String,
+    /// Command the raw frames are piped to instead of writing PNGs, unless empty.
+    recording_encoder: String,
+    recording_every: usize,
+    /// Whether the simulation stopped by itself before the recording, it can't while recording.
+    recording_auto_stop: bool,
+    recording_status: String,
+    /// Pixel clicked in the last frame, resolved through the ID buffer while rendering.
+    pick_request: Option<(u32, u32, PickAction)>,
+    picked_edge: Option<EdgeId>,
+    grab: Option<Grab>,
 }
 
 impl Application {
@@ -64,13 +128,12 @@ impl Application {
         let projection = Mat4::perspective_rh(1.2, aspect_ratio, 0.01, 10.);
         let transform_pers = projection * translate;
         graph_renderer.lock().unwrap().transform(transform_pers * view_transform);
-
-        let screen_translate = Mat4::from_translation(Vec3::new(width, height, 1.));
-        let screen_scale = Mat4::from_scale(Vec3::new(width * 2., height * 2., 1.));
-        let screen_transform = screen_translate * screen_scale;
+        graph_renderer.lock().unwrap().picking = true;
 
         let world = World::new();
         let mut physics_components = PhysicsComponent::new();
+        // Keep the world's positions close to the simulation for picking
+        physics_components.readback_interval = 10;
 
         Self {
             physics_components,
@@ -79,12 +142,221 @@ impl Application {
             graph_renderer: graph_renderer.clone(),
             screen_transform_ortho,
             transform_pers,
-            screen_transform,
             view_transform,
             perspective_camera: true,
             step_speed: 1,
             frame: 0,
             auto_rotate: false,
+            tool: Tool::Rotate,
+            drag_path: vec![],
+            shrink_buffers: false,
+            relation: String::new(),
+            project_path: "mycelia.json".to_string(),
+            project_request: None,
+            project_status: String::new(),
+            screenshot_path: "screenshot.png".to_string(),
+            screenshot_size: [2048, 2048],
+            screenshot_requested: false,
+            vector_path: "view.svg".to_string(),
+            vector_requested: false,
+            screenshot_status: String::new(),
+            recording: None,
+            recording_toggled: false,
+            recording_directory: "recording".to_string(),
+            recording_encoder: String::new(),
+            recording_every: 1,
+            recording_auto_stop: true,
+            recording_status: String::new(),
+            pick_request: None,
+            picked_edge: None,
+            grab: None,
+        }
+    }
+
+    /// The transform the graph is rendered with.
+    fn camera_transform(&self) -> Mat4 {
+        if self.perspective_camera {
+            self.transform_pers * self.view_transform
+        } else {
+            self.screen_transform_ortho * self.view_transform
+        }
+    }
+
+    fn perspective_transform(camera_dist: f32) -> Mat4 {
+        let width = 1080.;
+        let height = 1080.;
+        let aspect_ratio = width / height;
+        let translate = Mat4::from_translation(Vec3::new(0., 0., 1. / -camera_dist));
+        let projection = Mat4::perspective_rh(1.2, aspect_ratio, 0.01, 10.);
+        projection * translate
+    }
+
+    fn project_settings(&self) -> ProjectSettings {
+        ProjectSettings {
+            camera: Camera {
+                view_transform: self.view_transform,
+                camera_dist: self.camera_dist,
+                perspective: self.perspective_camera,
+            },
+            physics: self.physics_components.parameters,
+        }
+    }
+
+    fn apply_project_settings(&mut self, settings: &ProjectSettings) {
+        self.view_transform = settings.camera.view_transform;
+        self.camera_dist = settings.camera.camera_dist;
+        self.perspective_camera = settings.camera.perspective;
+        self.transform_pers = Self::perspective_transform(self.camera_dist);
+
+        self.physics_components.parameters = settings.physics;
+    }
+
+    /// Saves or opens the project file, needs the renderer to exchange the layout with the GPU.
+    fn handle_project_request(&mut self, renderer: &mut Renderer) {
+        let Some(request) = self.project_request.take() else { return; };
+        let world = self.world.clone();
+        let mut world = world.lock().unwrap();
+
+        match request {
+            ProjectRequest::Save => {
+                self.physics_components.read_positions(renderer, &mut world);
+                let settings = self.project_settings();
+                self.project_status = match world.save_project(&settings, &self.project_path) {
+                    Ok(()) => format!("Saved {}", self.project_path),
+                    Err(e) => format!("Failed to save {}: {}", self.project_path, e),
+                };
+            }
+            ProjectRequest::Open => {
+                match World::load_project(&self.project_path) {
+                    Ok((project, settings)) => {
+                        *world = project;
+                        world.take_changes();
+                        self.physics_components.set_nodes(renderer, &world);
+                        self.apply_project_settings(&settings);
+                        self.project_status = format!("Opened {}", self.project_path);
+                    }
+                    Err(e) => self.project_status = format!("Failed to open {}: {}", self.project_path, e),
+                }
+            }
+        }
+    }
+
+    /// Renders the current view at the screenshot resolution and saves it as a PNG.
+    fn handle_screenshot_request(&mut self, renderer: &mut Renderer) {
+        if !std::mem::take(&mut self.screenshot_requested) {
+            return;
+        }
+        let [width, height] = self.screenshot_size;
+        let image = self.graph_renderer.lock().unwrap().render_offscreen(renderer, width, height);
+        self.screenshot_status = match image.save_png(&self.screenshot_path) {
+            Ok(()) => format!("Saved {}", self.screenshot_path),
+            Err(e) => format!("Failed to save {}: {}", self.screenshot_path, e),
+        };
+    }
+
+    /// Exports the current view as SVG or PDF, with the positions of the simulation.
+    fn handle_vector_request(&mut self, renderer: &mut Renderer) {
+        if !std::mem::take(&mut self.vector_requested) {
+            return;
+        }
+        let world = self.world.clone();
+        let mut world = world.lock().unwrap();
+        self.physics_components.read_positions(renderer, &mut world);
+        let [width, height] = self.screenshot_size;
+        let image = vectorize(&world, self.camera_transform(), width, height);
+        self.screenshot_status = match image.save(&self.vector_path) {
+            Ok(()) => format!("Saved {}", self.vector_path),
+            Err(e) => format!("Failed to save {}: {}", self.vector_path, e),
+        };
+    }
+
+    /// Starts or stops a recording when asked to, and captures the frames of a running one.
+    fn handle_recording(&mut self, renderer: &mut Renderer) {
+        if std::mem::take(&mut self.recording_toggled) {
+            if self.recording.is_some() {
+                self.stop_recording(None);
+            } else {
+                self.start_recording();
+            }
+        }
+
+        let Some(recorder) = self.recording.as_mut() else { return; };
+        if !recorder.next_frame() {
+            return;
+        }
+        let image = self.graph_renderer.lock().unwrap().render_offscreen(renderer, recorder.width, recorder.height);
+        if let Err(e) = recorder.capture(&image) {
+            self.stop_recording(Some(e));
+        }
+    }
+
+    fn start_recording(&mut self) {
+        let [width, height] = self.screenshot_size;
+        let recorder = if self.recording_encoder.trim().is_empty() {
+            Recorder::to_directory(&self.recording_directory, width, height, self.recording_every)
+        } else {
+            Recorder::to_encoder(&self.recording_encoder, width, height, self.recording_every)
+        };
+        match recorder {
+            Ok(recorder) => {
+                // Every frame runs the same steps, stopping depends on how late the energy is read
+                self.recording_auto_stop = self.physics_components.auto_stop;
+                self.physics_components.auto_stop = false;
+                self.physics_components.running = true;
+                self.frame = 0;
+                self.recording = Some(recorder);
+            }
+            Err(e) => self.recording_status = format!("Failed to start recording: {}", e),
+        }
+    }
+
+    fn stop_recording(&mut self, error: Option<std::io::Error>) {
+        let Some(recorder) = self.recording.take() else { return; };
+        self.physics_components.auto_stop = self.recording_auto_stop;
+        let captured = recorder.captured();
+        // The encoder is waited for in any case, a failed capture is the more useful error
+        let finished = recorder.finish();
+        let result = error.map_or(finished, Err);
+        self.recording_status = match result {
+            Ok(()) => format!("Recorded {} frames", captured),
+            Err(e) => format!("Recording failed after {} frames: {}", captured, e),
+        };
+    }
+
+    /// Selects or grabs the node under the clicked pixel, or remembers the edge under it.
+    fn handle_pick_request(&mut self, renderer: &mut Renderer) {
+        let Some((x, y, action)) = self.pick_request.take() else { return; };
+        let world = self.world.clone();
+        let mut world = world.lock().unwrap();
+        let mut graph_renderer = self.graph_renderer.lock().unwrap();
+        // Ids of a frame drawn before nodes were removed or edges changed could name others now
+        if !graph_renderer.has_ids(&self.physics_components) {
+            return;
+        }
+        let picked = graph_renderer.pick(renderer, &world, &self.physics_components, x, y);
+        drop(graph_renderer);
+
+        let mode = match action {
+            PickAction::Select(mode) => mode,
+            PickAction::Grab => {
+                // The button may have been released before the frame was rendered
+                if let (Some(Picked::Node(id)), false) = (picked, self.drag_path.is_empty()) {
+                    // Start from where the simulation has the node
+                    self.physics_components.read_positions(renderer, &mut world);
+                    self.grab = Some(Grab::new(&mut world, id));
+                }
+                return;
+            }
+        };
+
+        self.picked_edge = None;
+        match picked {
+            Some(Picked::Node(id)) => world.selection_mut().combine([id], mode),
+            Some(Picked::Edge(id)) => {
+                self.picked_edge = Some(id);
+                world.selection_mut().combine([], mode);
+            }
+            None => world.selection_mut().combine([], mode),
         }
     }
 }
@@ -103,68 +375,117 @@ impl GuiComponent for Application {
         let mut lock = self.world.lock().unwrap();
 
         // Gui code
+        let over_ui = context.is_pointer_over_area();
+        let screen = context.screen_rect().size();
+        let transform = self.camera_transform();
         context.input(|x| {
 
             if x.raw_scroll_delta.y != 0. {
                 self.camera_dist += x.raw_scroll_delta.y * 0.001;
-
-                let width = 1080.;
-                let height = 1080.;
-                let aspect_ratio = width / height;
-                let translate = Mat4::from_translation(Vec3::new(0., 0., 1. / -self.camera_dist));
-                let projection = Mat4::perspective_rh(1.2, aspect_ratio, 0.01, 10.);
-                self.transform_pers = projection * translate;
+                self.transform_pers = Self::perspective_transform(self.camera_dist);
             }
 
-            if x.pointer.button_down(egui::PointerButton::Primary) {
-                let rot_x = glam::Mat4::from_rotation_y(x.pointer.delta().x * 0.5 / 60.0);
-                let rot_y = glam::Mat4::from_rotation_x(-x.pointer.delta().y * 0.5 / 60.0);
-                self.view_transform = rot_x * rot_y * self.view_transform;
+            // Drags that start on a window belong to the window
+            if x.pointer.button_pressed(egui::PointerButton::Primary) && !over_ui {
+                self.drag_path = x.pointer.press_origin().into_iter().collect();
+
+                if let (Tool::Move, Some(origin)) = (self.tool, x.pointer.press_origin()) {
+                    if self.graph_renderer.lock().unwrap().can_pick() {
+                        self.pick_request = Some(((origin.x * x.pixels_per_point) as u32, (origin.y * x.pixels_per_point) as u32, PickAction::Grab));
+                    } else if let Some(id) = nearest(&lock, &transform, screen, origin) {
+                        self.grab = Some(Grab::new(&mut lock, id));
+                    }
+                }
             }
 
-            if x.pointer.button_pressed(egui::PointerButton::Primary) {
-
-                if let Some(mut p) = x.pointer.press_origin() {
-                    p = p * 2.;
-
-                    let mat = self.screen_transform * self.transform_pers * self.view_transform;
-                    let mut wp = mat.inverse() * Vec4::new(p.x, p.y, 0., 1.);
-                    wp = wp / wp.w;
-                    let mut wp_f = mat.inverse() * Vec4::new(p.x, p.y, 10., 1.);
-                    wp_f = wp_f / wp_f.w;
-                    let mut dir = (wp_f - wp).xyz().normalize();
-                    // println!("wp {:?}", wp);
-                    // println!("wpf {:?}", wp_f);
-                    // println!("dir {:?}", dir);
-
-                    // Do ray marching
-                    let mut t = 0.0;
-                    for _ in 0..100 {
-                        let rp = wp.xyz() + dir * t;
-                        let near = lock.nodes_mut().enumerate()
-                            .min_by_key(|(i, n)| {
-                                OrderedFloat((n.pos - rp).length() - 0.01)
-                            })
-                            .map(|(i, n)| {
-                                (i, (n.pos - rp).length() - 0.01)
-                            }).unwrap();
-
-                        t += near.1;
-
-                        if near.1 < 0.0001 {
-                            // We have a hit
-                            //lock.nodes_mut().nth( near.0 ).unwrap().selected = true;
-                            break;
+            if x.pointer.button_down(egui::PointerButton::Primary) && !self.drag_path.is_empty() {
+                match self.tool {
+                    Tool::Rotate => {
+                        let rot_x = glam::Mat4::from_rotation_y(x.pointer.delta().x * 0.5 / 60.0);
+                        let rot_y = glam::Mat4::from_rotation_x(-x.pointer.delta().y * 0.5 / 60.0);
+                        self.view_transform = rot_x * rot_y * self.view_transform;
+                    }
+                    Tool::Move => {
+                        // Synced to the node buffers before the next frame is simulated
+                        let grabbed = self.grab.as_ref().and_then(|g| Some((g.id, lock.node(g.id)?.pos)));
+                        if let (Some((id, pos)), Some(p)) = (grabbed, x.pointer.latest_pos()) {
+                            lock.set_position(id, unproject(&transform, pos, screen, p));
                         }
-
-                        if near.1 > 1000. {
-                            break;
+                    }
+                    Tool::Box => {
+                        if let Some(p) = x.pointer.latest_pos() {
+                            self.drag_path.truncate(1);
+                            self.drag_path.push(p);
+                        }
+                    }
+                    Tool::Lasso => {
+                        if let Some(p) = x.pointer.latest_pos() {
+                            if self.drag_path.last().is_none_or(|last| last.distance(p) > 2.) {
+                                self.drag_path.push(p);
+                            }
                         }
                     }
                 }
             }
+
+            if x.pointer.button_released(egui::PointerButton::Primary) && !self.drag_path.is_empty() {
+                let path = std::mem::take(&mut self.drag_path);
+                if let Some(grab) = self.grab.take() {
+                    grab.release(&mut lock);
+                }
+                let mode = if x.modifiers.command || x.modifiers.ctrl {
+                    SelectionMode::Toggle
+                } else if x.modifiers.shift {
+                    SelectionMode::Add
+                } else {
+                    SelectionMode::Replace
+                };
+
+                let end = x.pointer.latest_pos().unwrap_or(path[0]);
+                let on_screen = lock.nodes()
+                    .filter_map(|(id, n)| Some((id, project(&transform, n.pos, screen)?)))
+                    .collect::<Vec<_>>();
+                let within = |test: &dyn Fn(egui::Pos2) -> bool| {
+                    on_screen.iter().filter(|(_, p)| test(*p)).map(|(id, _)| *id).collect::<Vec<_>>()
+                };
+
+                let picking = self.graph_renderer.lock().unwrap().can_pick();
+                let picked = match (path[0].distance(end) >= 4., self.tool) {
+                    // A click picks what is visible under the cursor once the frame is rendered
+                    (false, _) if picking => {
+                        self.pick_request = Some(((end.x * x.pixels_per_point) as u32, (end.y * x.pixels_per_point) as u32, PickAction::Select(mode)));
+                        None
+                    }
+                    // Without the ID buffer, the closest node in screen space
+                    (false, _) => Some(nearest(&lock, &transform, screen, end).into_iter().collect()),
+                    // Dragging to rotate or move leaves the selection alone
+                    (true, Tool::Rotate | Tool::Move) => None,
+                    (true, Tool::Box) => {
+                        let rect = egui::Rect::from_two_pos(path[0], end);
+                        Some(within(&|p| rect.contains(p)))
+                    }
+                    (true, Tool::Lasso) => Some(within(&|p| inside(&path, p))),
+                };
+
+                if let Some(picked) = picked {
+                    lock.selection_mut().combine(picked, mode);
+                }
+            }
         });
 
+        // Outline of the box or lasso being dragged
+        if self.drag_path.len() > 1 && matches!(self.tool, Tool::Box | Tool::Lasso) {
+            let painter = context.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("selection")));
+            let stroke = egui::Stroke::new(1., egui::Color32::from_rgb(50, 200, 255));
+            if self.tool == Tool::Box {
+                painter.rect_stroke(egui::Rect::from_two_pos(self.drag_path[0], self.drag_path[1]), 0., stroke, egui::StrokeKind::Middle);
+            } else {
+                let mut points = self.drag_path.clone();
+                points.push(self.drag_path[0]);
+                painter.add(egui::Shape::line(points, stroke));
+            }
+        }
+
 
         // Show selected nodes' details
         // for n in self.selected_nodes.iter() {
@@ -222,33 +543,84 @@ impl GuiComponent for Application {
 
                 ui.label("Edge attraction");
                 ui.add(
-                    Slider::new(&mut self.physics_components.edge_attraction, 0.0..=20.0)
+                    Slider::new(&mut self.physics_components.parameters.edge_attraction, 0.0..=20.0)
                 );
                 ui.label("Repulsion");
                 ui.add(
-                    Slider::new(self.physics_components.repulsion(), 0.0..=4.0)
+                    Slider::new(&mut self.physics_components.parameters.repulsion, 0.0..=4.0)
                 );
                 ui.label("Center attraction");
                 ui.add(
-                    Slider::new(lock.get_center_attraction_mut(), 0.0..=20200.0)
+                    Slider::new(&mut self.physics_components.parameters.center_attraction, 0.0..=0.1)
                 );
+                ui.horizontal(|ui| {
+                    ui.label("Repulsion between");
+                    let mode = &mut self.physics_components.parameters.repulsion_mode;
+                    ui.radio_value(mode, RepulsionMode::AllPairs, "All pairs");
+                    ui.radio_value(mode, RepulsionMode::BarnesHut, "Barnes–Hut");
+                    ui.radio_value(mode, RepulsionMode::SpatialHash, "Neighbours");
+                });
+                match self.physics_components.parameters.repulsion_mode {
+                    RepulsionMode::AllPairs => {}
+                    RepulsionMode::BarnesHut => {
+                        ui.label("Barnes–Hut theta");
+                        ui.add(
+                            Slider::new(&mut self.physics_components.parameters.theta, 0.0..=1.5)
+                        );
+                    }
+                    RepulsionMode::SpatialHash => {
+                        ui.label("Repulsion cutoff");
+                        ui.add(
+                            Slider::new(&mut self.physics_components.parameters.cutoff, 0.01..=0.5)
+                        );
+                    }
+                }
+
+                ui.label("Time step");
+                ui.add(Slider::new(&mut self.physics_components.parameters.time_step, 0.1..=2.0));
+                ui.label("Mass");
+                ui.add(Slider::new(&mut self.physics_components.parameters.mass, 0.1..=10.0).logarithmic(true));
+                ui.label("Damping");
+                ui.add(Slider::new(&mut self.physics_components.parameters.damping, 0.0..=1.0));
+                ui.label("Cooling");
+                ui.add(Slider::new(&mut self.physics_components.parameters.cooling, 0.98..=1.0));
+                ui.label("Max displacement");
+                ui.add(Slider::new(&mut self.physics_components.parameters.max_displacement, 0.001..=0.2).logarithmic(true));
+                ui.horizontal(|ui| {
+                    ui.label(format!("Temperature {:.3}", self.physics_components.temperature));
+                    if ui.button("Reheat").clicked() {
+                        self.physics_components.reheat();
+                    }
+                });
+                ui.add(Checkbox::new(&mut self.physics_components.auto_stop, "Stop when settled"));
+                ui.label("Settled below displacement");
+                ui.add(Slider::new(&mut self.physics_components.convergence_threshold, 0.00001..=0.01).logarithmic(true));
+                let history = self.physics_components.energy_history();
+                if let Some(last) = history.back() {
+                    ui.label(format!("Energy {:.3e}, largest step {:.3e}", last.kinetic_energy, last.max_displacement));
+                }
+                if self.physics_components.converged() {
+                    ui.label("Settled, editing the graph or a parameter resumes");
+                }
+                energy_plot(ui, history.iter().map(|e| e.kinetic_energy));
 
                 ui.add(Checkbox::new(&mut self.perspective_camera, "Use perspective camera"));
 
-                ui.add(Checkbox::new(&mut self.physics_components.running, "simulate"));
-                if ui.button("Step").clicked() {
-                    self.physics_components.step = true;
-                }
+                ui.horizontal(|ui| {
+                    ui.add(Checkbox::new(&mut self.physics_components.running, "simulate"));
+                    if ui.button("Step").clicked() {
+                        self.physics_components.step = true;
+                    }
+                });
+                ui.label("Steps per frame");
+                ui.add(Slider::new(&mut self.physics_components.steps_per_frame, 1..=20));
 
-                if self.perspective_camera {
-                    self.graph_renderer.lock().unwrap().transform(self.transform_pers * self.view_transform);
-                } else {
-                    self.graph_renderer.lock().unwrap().transform(self.screen_transform_ortho * self.view_transform);
-                }
 
-                if ui.button("Activate").clicked() {
+                if ui.button("Activate").clicked() && lock.node_count() > 0 {
                     let c = lock.node_count();
-                    lock.nodes_mut().nth(random::<usize>() % c).unwrap().level += 1.;
+                    let id = lock.node_at_slot(random::<usize>() % c).unwrap();
+                    let level = lock.node(id).unwrap().level;
+                    lock.set_level(id, level + 1.);
                 }
 
                 ui.add(Slider::new(&mut self.step_speed, RangeInclusive::new(0, 100)));
@@ -257,29 +629,209 @@ impl GuiComponent for Application {
                 }
                 self.physics_components.update_weights(&lock);
 
-                // if ui.button("Randomize").clicked() {
-                //     lock.randomize();
-                // }
-
-                // if ui.button("Connect").clicked() {
-                //     for n in self.selected_nodes.chunks(2) {
-                //         lock.add_edge(n[0], n[1]);
-                //     }
-                // }
-
-                // if ui.button("Remove connection").clicked() {
-                //     for n in self.selected_nodes.chunks(2) {
-                //         lock.delete_edge(n[0], n[1]);
-                //     }
-                // }
-
-                // if ui.button("Add node").clicked() {
-                //     if let Some(n) = self.selected_nodes.first() {
-                //         lock.add_node();
-                //         let id = lock.get_nodes_mut().len() - 1;
-                //         lock.add_edge(*n, id);
-                //     }
-                // }
+                ui.horizontal(|ui| {
+                    ui.label("Drag to");
+                    ui.radio_value(&mut self.tool, Tool::Rotate, "Rotate");
+                    ui.radio_value(&mut self.tool, Tool::Move, "Move");
+                    ui.radio_value(&mut self.tool, Tool::Box, "Box select");
+                    ui.radio_value(&mut self.tool, Tool::Lasso, "Lasso select");
+                });
+                {
+                    let mut graph_renderer = self.graph_renderer.lock().unwrap();
+                    ui.add(Checkbox::new(&mut graph_renderer.picking, "Pick with ID buffer"));
+                    if graph_renderer.picking && !graph_renderer.can_pick() {
+                        ui.label(format!("Too large for the ID buffer (over {} nodes or edge entries), picking the nearest node", MAX_PICK_IDS));
+                    }
+                }
+                ui.label(format!("{} nodes selected", lock.selection().len()));
+                ui.horizontal(|ui| {
+                    if ui.button("All").clicked() {
+                        lock.select_all();
+                    }
+                    if ui.button("None").clicked() {
+                        lock.selection_mut().clear();
+                    }
+                    if ui.button("Invert").clicked() {
+                        lock.invert_selection();
+                    }
+                    if ui.button("Pin").clicked() {
+                        lock.pin_selected(true);
+                    }
+                    if ui.button("Unpin").clicked() {
+                        lock.pin_selected(false);
+                    }
+                });
+
+                if ui.button("Randomize").clicked() {
+                    lock.randomize();
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Relation");
+                    ui.text_edit_singleline(&mut self.relation);
+                });
+                let selected = lock.selection().as_slice().to_vec();
+                if ui.button("Connect").clicked() {
+                    for n in selected.chunks(2) {
+                        if let [a, b] = n {
+                            if let Some(edge) = lock.add_edge(*a, *b) {
+                                if !self.relation.is_empty() {
+                                    lock.set_relation(edge, self.relation.clone());
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if ui.button("Remove connection").clicked() {
+                    for n in selected.chunks(2) {
+                        if let [a, b] = n {
+                            if let Some(edge) = lock.find_edge(*a, *b).or(lock.find_edge(*b, *a)) {
+                                lock.remove_edge(edge);
+                            }
+                        }
+                    }
+                }
+
+                if ui.button("Add node").clicked() {
+                    if let Some(n) = lock.selection().first() {
+                        let pos = lock.node(n).unwrap().pos + Node::new_random(0.).pos * 0.1;
+                        let id = lock.add_node(Node::new(pos, 0.));
+                        lock.add_edge(n, id);
+                    } else {
+                        lock.add_node(Node::new_random(0.));
+                    }
+                }
+
+                if ui.button("Remove nodes").clicked() {
+                    lock.remove_selected();
+                }
+
+                if ui.button("Clear").clicked() {
+                    lock.clear();
+                }
+
+                let (node_capacity, edge_capacity) = self.physics_components.capacity();
+                ui.label(format!("Buffer capacity: {} nodes, {} edges", node_capacity, edge_capacity));
+                if ui.button("Shrink buffers").clicked() {
+                    self.shrink_buffers = true;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Read back layout every");
+                    ui.add(Slider::new(&mut self.physics_components.readback_interval, 0..=120).suffix(" frames"));
+                });
+                if ui.button("Read back layout").clicked() {
+                    self.physics_components.request_readback();
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Project");
+                    ui.text_edit_singleline(&mut self.project_path);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        self.project_request = Some(ProjectRequest::Save);
+                    }
+                    if ui.button("Open").clicked() {
+                        self.project_request = Some(ProjectRequest::Open);
+                    }
+                });
+                if !self.project_status.is_empty() {
+                    ui.label(&self.project_status);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Screenshot");
+                    ui.text_edit_singleline(&mut self.screenshot_path);
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.screenshot_size[0]).range(16..=8192));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(&mut self.screenshot_size[1]).range(16..=8192));
+                    if ui.button("Save screenshot").clicked() {
+                        self.screenshot_requested = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Vector");
+                    ui.text_edit_singleline(&mut self.vector_path);
+                    if ui.button("Export").clicked() {
+                        self.vector_requested = true;
+                    }
+                });
+                if !self.screenshot_status.is_empty() {
+                    ui.label(&self.screenshot_status);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Record every");
+                    ui.add(egui::DragValue::new(&mut self.recording_every).range(1..=100));
+                    ui.label("frames");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Directory");
+                    ui.text_edit_singleline(&mut self.recording_directory);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Encoder");
+                    ui.text_edit_singleline(&mut self.recording_encoder)
+                        .on_hover_text("Pipes raw RGBA frames to this command instead of writing PNGs, e.g.\nffmpeg -f rawvideo -pix_fmt rgba -s {width}x{height} -i - simulation.mp4");
+                });
+                let label = if self.recording.is_some() { "Stop recording" } else { "Record" };
+                if ui.button(label).clicked() {
+                    self.recording_toggled = true;
+                }
+                if let Some(recorder) = &self.recording {
+                    ui.label(format!("Recording, {} frames captured", recorder.captured()));
+                } else if !self.recording_status.is_empty() {
+                    ui.label(&self.recording_status);
+                }
+            });
+
+        self.graph_renderer.lock().unwrap().transform(self.camera_transform());
+
+        egui::Window::new("Selection")
+            .resizable(true)
+            .title_bar(true)
+            .show(context, |ui| {
+                if let Some(edge) = self.picked_edge.and_then(|id| lock.edge(id)) {
+                    let name = |id| lock.label(id).map(str::to_string).unwrap_or_else(|| format!("{:?}", id));
+                    ui.label(format!("Edge {} → {}, weight {}", name(edge.source), name(edge.target), edge.weight));
+                    if let Some(relation) = lock.relation(self.picked_edge.unwrap()) {
+                        ui.label(format!("Relation: {}", relation));
+                    }
+                    ui.separator();
+                }
+                let selected = lock.selection().as_slice().to_vec();
+                for id in selected.iter() {
+                    let Some(attributes) = lock.attributes_mut(*id) else { continue; };
+                    let title = attributes.label.clone().unwrap_or_else(|| format!("{:?}", id));
+                    egui::CollapsingHeader::new(title)
+                        .id_salt(id)
+                        .show(ui, |ui| {
+                            let mut label = attributes.label.clone().unwrap_or_default();
+                            ui.horizontal(|ui| {
+                                ui.label("Label");
+                                if ui.text_edit_singleline(&mut label).changed() {
+                                    attributes.label = Some(label);
+                                }
+                            });
+                            let mut kind = attributes.kind.clone().unwrap_or_default();
+                            ui.horizontal(|ui| {
+                                ui.label("Type");
+                                if ui.text_edit_singleline(&mut kind).changed() {
+                                    attributes.kind = Some(kind);
+                                }
+                            });
+                            for (key, value) in attributes.properties.iter() {
+                                ui.label(format!("{}: {}", key, value));
+                            }
+                        });
+                }
             });
     }
 }
@@ -288,21 +840,102 @@ impl RenderComponent for Application {
     fn initialize(&mut self, renderer: &mut Renderer) {
         self.physics_components.initialize(renderer);
         self.graph_renderer.lock().unwrap().initialize(renderer);
-        self.physics_components.set_nodes(&self.world.lock().unwrap());
+        self.physics_components.sync(renderer, &mut self.world.lock().unwrap());
     }
 
     fn render(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, swapchain_image: &Image, swapchain_image_view: &ImageView) {
-        self.graph_renderer.lock().unwrap().graph_data(*self.physics_components.node_count(), self.physics_components.node_buffer(), self.physics_components.edge_count(), self.physics_components.edge_buffer());
+        self.physics_components.sync(renderer, &mut self.world.lock().unwrap());
+        // After syncing, so grabbing a node can read back the current layout
+        self.handle_pick_request(renderer);
+        self.handle_project_request(renderer);
+        self.physics_components.poll_readback(renderer, &mut self.world.lock().unwrap());
+        self.physics_components.poll_energy(renderer);
+        if self.shrink_buffers {
+            self.physics_components.shrink_to_fit(renderer);
+            self.shrink_buffers = false;
+        }
+        self.graph_renderer.lock().unwrap().graph_data(*self.physics_components.node_count(), self.physics_components.node_buffer(), self.physics_components.edge_count(), self.physics_components.edge_buffer(), self.physics_components.slot_generation());
+        self.handle_screenshot_request(renderer);
+        self.handle_vector_request(renderer);
+        self.handle_recording(renderer);
         self.physics_components.render(renderer, command_buffer, swapchain_image, swapchain_image_view);
         self.graph_renderer.lock().unwrap().render(renderer, command_buffer, swapchain_image, swapchain_image_view);
     }
 }
 
+/// Plots the energy of the most recent steps on a logarithmic scale.
+fn energy_plot(ui: &mut egui::Ui, energies: impl ExactSizeIterator<Item = f32>) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(ui.available_width(), 60.), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2., ui.visuals().extreme_bg_color);
+
+    let count = energies.len();
+    let values = energies.map(|e| e.max(f32::MIN_POSITIVE).log10()).collect::<Vec<f32>>();
+    if count < 2 {
+        return;
+    }
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = (max - min).max(f32::EPSILON);
+    let points = values.iter().enumerate().map(|(i, v)| egui::pos2(
+        rect.left() + rect.width() * i as f32 / (count - 1) as f32,
+        rect.bottom() - rect.height() * (v - min) / range,
+    )).collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1., ui.visuals().text_color())));
+}
+
+/// Position of a world point on the screen in egui points, matching the projection of the graph
+/// shader.
+fn project(transform: &Mat4, pos: Vec3, screen: Vec2) -> Option<egui::Pos2> {
+    let clip = *transform * pos.extend(1.);
+    if clip.w < 0. {
+        return None;
+    }
+    let ndc = clip.xy() / clip.w;
+    Some(egui::pos2(ndc.x * screen.x + screen.x / 2., ndc.y * screen.y + screen.y / 2.))
+}
+
+/// World position under a screen point, at the depth of `pos` so it moves in the camera plane.
+fn unproject(transform: &Mat4, pos: Vec3, screen: Vec2, point: egui::Pos2) -> Vec3 {
+    let clip = *transform * pos.extend(1.);
+    let ndc = (point - screen / 2.).to_vec2() / screen;
+    let world = transform.inverse() * Vec4::new(ndc.x * clip.w, ndc.y * clip.w, clip.z, clip.w);
+    world.xyz() / world.w
+}
+
+/// The node projected closest to a screen point, if it is within 10 points.
+fn nearest(world: &World, transform: &Mat4, screen: Vec2, point: egui::Pos2) -> Option<NodeId> {
+    world.nodes()
+        .filter_map(|(id, n)| Some((id, project(transform, n.pos, screen)?.distance(point))))
+        .filter(|(_, distance)| *distance < 10.)
+        .min_by_key(|(_, distance)| OrderedFloat(*distance))
+        .map(|(id, _)| id)
+}
+
+/// Even-odd test of a point against a closed polygon.
+fn inside(polygon: &[egui::Pos2], p: egui::Pos2) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize .env environment variables
     dotenv().ok();
 
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if args.first().is_some_and(|command| command == "layout") {
+        std::process::exit(cli::layout(&args[1..]));
+    }
+
     let renderer = Arc::new(Mutex::new(GraphRenderer::new()));
     let application = Arc::new(Mutex::new(Application::new(renderer.clone()).await));
     App::run(
@@ -0,0 +1,178 @@
+//! Native project files that save a complete session.
+//!
+//! A project is a JSON document holding the graph with its attributes and current layout, the
+//! camera and the physics parameters. Every document carries a `version`; documents written by
+//! older versions are upgraded step by step before they are read.
+
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use crate::attributes::{EdgeAttributes, NodeAttributes};
+use crate::gpu_physics::PhysicsParameters;
+use crate::io::Error;
+use crate::world::{Node, World};
+
+/// Upgrades of the document layout, the entry at `i` turns a version `i + 1` document into a
+/// version `i + 2` document.
+const MIGRATIONS: &[fn(&mut Json)] = &[
+    physics_parameters_v2,
+];
+
+/// Version of the documents written by this build.
+pub const VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Version 1 saved a center attraction the shaders ignored and none of the integrator parameters,
+/// those start out at their defaults.
+fn physics_parameters_v2(document: &mut Json) {
+    let Some(physics) = document.pointer_mut("/settings/physics").and_then(Json::as_object_mut) else { return; };
+    physics.remove("center_attraction");
+    if let Json::Object(defaults) = serde_json::to_value(PhysicsParameters::default()).unwrap() {
+        for (key, value) in defaults {
+            physics.entry(key).or_insert(value);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Camera {
+    pub view_transform: Mat4,
+    /// Distance of the perspective camera from the origin.
+    pub camera_dist: f32,
+    pub perspective: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            view_transform: Mat4::IDENTITY,
+            camera_dist: 1.2,
+            perspective: true,
+        }
+    }
+}
+
+/// Everything saved in a project besides the graph.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    pub camera: Camera,
+    pub physics: PhysicsParameters,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProjectNode {
+    position: Vec3,
+    level: f32,
+    #[serde(default)]
+    pinned: bool,
+    attributes: NodeAttributes,
+}
+
+/// Edges refer to their nodes by index in the node list.
+#[derive(Serialize, Deserialize)]
+struct ProjectEdge {
+    source: usize,
+    target: usize,
+    weight: f32,
+    attributes: EdgeAttributes,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document {
+    version: u32,
+    directed: bool,
+    nodes: Vec<ProjectNode>,
+    edges: Vec<ProjectEdge>,
+    settings: ProjectSettings,
+}
+
+fn json_error(error: serde_json::Error) -> Error {
+    match error.classify() {
+        serde_json::error::Category::Io => Error::Io(error.into()),
+        _ => Error::Parse { line: error.line(), message: error.to_string() },
+    }
+}
+
+/// Brings a document of any older version up to [`VERSION`].
+fn migrate(document: &mut Json) -> Result<(), Error> {
+    let version = document.get("version")
+        .and_then(Json::as_u64)
+        .ok_or_else(|| Error::Invalid("the project has no version".to_string()))? as u32;
+
+    if version == 0 || version > VERSION {
+        return Err(Error::Invalid(format!("unsupported project version {}, this build reads up to version {}", version, VERSION)));
+    }
+
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(document);
+    }
+    document["version"] = Json::from(VERSION);
+    Ok(())
+}
+
+impl World {
+    /// Restores a world and its settings from a project document.
+    pub fn read_project(input: &str) -> Result<(World, ProjectSettings), Error> {
+        let mut document = serde_json::from_str::<Json>(input).map_err(json_error)?;
+        migrate(&mut document)?;
+        let document = serde_json::from_value::<Document>(document).map_err(|e| Error::Invalid(e.to_string()))?;
+
+        let mut world = World::empty();
+        world.set_directed(document.directed);
+
+        let ids = document.nodes.into_iter()
+            .map(|n| world.add_node_with(Node { pinned: n.pinned, ..Node::new(n.position, n.level) }, n.attributes))
+            .collect::<Vec<_>>();
+
+        for edge in document.edges {
+            let (Some(source), Some(target)) = (ids.get(edge.source), ids.get(edge.target)) else {
+                return Err(Error::Invalid(format!("edge between {} and {} references a missing node", edge.source, edge.target)));
+            };
+            world.add_edge_with(*source, *target, edge.weight, edge.attributes);
+        }
+
+        Ok((world, document.settings))
+    }
+
+    pub fn load_project(path: impl AsRef<Path>) -> Result<(World, ProjectSettings), Error> {
+        Self::read_project(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the world and the settings as a project document of the current [`VERSION`].
+    pub fn write_project(&self, settings: &ProjectSettings, writer: &mut impl Write) -> Result<(), Error> {
+        let document = Document {
+            version: VERSION,
+            directed: self.is_directed(),
+            nodes: self.nodes()
+                .map(|(id, node)| ProjectNode {
+                    position: node.pos,
+                    level: node.level,
+                    pinned: node.pinned,
+                    attributes: self.attributes(id).unwrap().clone(),
+                })
+                .collect(),
+            edges: self.edges()
+                .map(|(id, edge)| ProjectEdge {
+                    source: self.node_slot(edge.source).unwrap(),
+                    target: self.node_slot(edge.target).unwrap(),
+                    weight: edge.weight,
+                    attributes: self.edge_attributes(id).unwrap().clone(),
+                })
+                .collect(),
+            settings: *settings,
+        };
+        serde_json::to_writer_pretty(&mut *writer, &document).map_err(json_error)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    pub fn save_project(&self, settings: &ProjectSettings, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_project(settings, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
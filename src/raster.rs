@@ -0,0 +1,207 @@
+//! Images of the graph outside of the window.
+//!
+//! [`GraphRenderer::render_offscreen`](crate::GraphRenderer::render_offscreen) renders with the
+//! compute shaders at any resolution, [`rasterize`] draws the same rings and lines on the CPU for
+//! machines without a GPU and for tests.
+
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use glam::{IVec2, Mat4, Vec2, Vec3, Vec4Swizzles};
+use crate::world::World;
+
+/// An 8 bit RGBA image, rows from top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// An opaque black image, the background the renderer clears to.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: [0, 0, 0, 255].repeat(width as usize * height as usize),
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * self.width + x) as usize * 4;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    /// Writes a pixel like `imageStore` does, outside of the image nothing happens.
+    fn store(&mut self, p: IVec2, color: Vec3) {
+        if p.x < 0 || p.y < 0 || p.x >= self.width as i32 || p.y >= self.height as i32 {
+            return;
+        }
+        let i = (p.y as usize * self.width as usize + p.x as usize) * 4;
+        let color = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.).round();
+        self.pixels[i..i + 3].copy_from_slice(&[color.x as u8, color.y as u8, color.z as u8]);
+    }
+
+    pub fn write_png(&self, writer: impl Write) -> std::io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+        writer.write_image_data(&self.pixels).map_err(std::io::Error::other)?;
+        writer.finish().map_err(std::io::Error::other)
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png(&mut writer)?;
+        writer.flush()
+    }
+}
+
+/// Radius of the node rings in `graph.comp`, divided by the camera distance.
+pub(crate) const NODE_SIZE: f32 = 12.;
+
+/// Radii of the density rings and the selection rings of a node, in units of [`NODE_SIZE`].
+pub(crate) const DENSITY_RINGS: [f32; 3] = [0.5, 0.9, 0.6];
+pub(crate) const SELECTION_RINGS: [f32; 2] = [1.5, 1.7];
+pub(crate) const NODE_RING: f32 = 1.1;
+pub(crate) const SELECTION_COLOR: Vec3 = Vec3::new(0.2, 0.8, 1.);
+
+fn palette(t: f32, a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> Vec3 {
+    a + b * Vec3::from_array((TAU * (c * t + d)).to_array().map(f32::cos))
+}
+
+/// Colour of a node's density rings, and of edges near it.
+pub(crate) fn density_color(level: f32, base: Vec3) -> Vec3 {
+    let t = level.clamp(0., 1.);
+    t * Vec3::new(1., 1., 0.) + (1. - t) * base
+}
+
+/// Colour of an edge without density, by the index of its relation type.
+pub(crate) fn relation_color(relation: u32) -> Vec3 {
+    if relation == 0 {
+        return Vec3::new(1., 0., 1.) * 0.2;
+    }
+    palette((relation as f32 * 0.618034).fract(), Vec3::splat(0.5), Vec3::splat(0.5), Vec3::ONE, Vec3::new(0., 0.33, 0.67)) * 0.4
+}
+
+/// Screen position and camera distance of a world point, `None` behind the camera.
+pub(crate) fn project(transform: &Mat4, p: Vec3, width: u32, height: u32) -> Option<(Vec3, f32)> {
+    let clip = *transform * p.extend(1.);
+    if clip.w < 0. {
+        return None;
+    }
+    let ndc = clip.xyz() / clip.w;
+    Some((Vec3::new(ndc.x * width as f32 + width as f32 / 2., ndc.y * height as f32 + height as f32 / 2., ndc.z), clip.w))
+}
+
+fn circle(image: &mut RgbaImage, pos: Vec3, r: f32, color: Vec3) {
+    let p = pos.truncate().as_ivec2();
+    let step = TAU / 190.;
+    let mut a = 0f32;
+    while a < TAU {
+        image.store(p + IVec2::new((a.cos() * r) as i32, (a.sin() * r) as i32), color);
+        a += step;
+    }
+}
+
+/// Part of the line from `a` to `b` inside the image as interpolation parameters, like
+/// `clip_line` in `edges.comp`.
+fn clip_line(a: Vec2, b: Vec2, size: Vec2) -> Option<(f32, f32)> {
+    let d = b - a;
+    let (mut t0, mut t1) = (0f32, 1f32);
+    for axis in 0..2 {
+        for (p, q) in [(-d[axis], a[axis]), (d[axis], size[axis] - 1. - a[axis])] {
+            if p == 0. {
+                if q < 0. {
+                    return None;
+                }
+            } else if p < 0. {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+    }
+    (t0 <= t1).then_some((t0, t1))
+}
+
+fn dda_line(image: &mut RgbaImage, a: Vec2, b: Vec2, c0: Vec3, c1: Vec3) {
+    let Some((start, end)) = clip_line(a, b, Vec2::new(image.width as f32, image.height as f32)) else { return; };
+    let p0 = a.lerp(b, start).as_ivec2();
+    let p1 = a.lerp(b, end).as_ivec2();
+
+    let delta = (p1 - p0).abs();
+    let step = (p1 - p0).signum();
+    let mut offset = delta / 2;
+    let mut p = p0;
+    let length = (p0 - p1).as_vec2().length();
+    let (major, minor) = if delta.x >= delta.y { (0, 1) } else { (1, 0) };
+    for _ in 0..delta[major] {
+        let t = start + (end - start) * (p0 - p).as_vec2().length() / length;
+        image.store(p, t * c1 + (1. - t) * c0);
+        offset[minor] += delta[minor];
+        if offset[minor] >= delta[major] {
+            offset[minor] -= delta[major];
+            p[minor] += step[minor];
+        }
+        p[major] += step[major];
+    }
+}
+
+/// Draws the world like `graph.comp` and `edges.comp` do, with the same transform.
+pub fn rasterize(world: &World, transform: Mat4, width: u32, height: u32) -> RgbaImage {
+    let mut image = RgbaImage::new(width, height);
+
+    for (id, node) in world.nodes() {
+        let Some((pos, d)) = project(&transform, node.pos, width, height) else { continue; };
+
+        if node.level > 0. {
+            let color = density_color(node.level, relation_color(0));
+            for r in DENSITY_RINGS {
+                circle(&mut image, pos, 1. / d * r * NODE_SIZE, color);
+            }
+        }
+
+        let mut color = Vec3::ONE;
+        if world.is_selected(id) {
+            color = SELECTION_COLOR;
+            for r in SELECTION_RINGS {
+                circle(&mut image, pos, 1. / d * r * NODE_SIZE, color);
+            }
+        }
+        circle(&mut image, pos, 1. / d * NODE_RING * NODE_SIZE, color);
+    }
+
+    // The edge buffer holds both directions of every edge, both are drawn
+    for (id, edge) in world.edges() {
+        let source = world.node(edge.source).unwrap();
+        let target = world.node(edge.target).unwrap();
+        let relation = world.relation_index(id);
+        for (n0, n1) in [(source, target), (target, source)] {
+            let (Some((p0, _)), Some((p1, _))) = (project(&transform, n0.pos, width, height), project(&transform, n1.pos, width, height)) else { continue; };
+
+            let base = relation_color(relation);
+            let c0 = density_color(n0.level, base);
+            let c1 = density_color(n1.level, base);
+
+            let ip0 = p0.truncate().floor();
+            let ip1 = p1.truncate().floor();
+            for (o0, o1) in [
+                (Vec2::new(0., 0.), Vec2::new(0., 0.)),
+                (Vec2::new(1., 1.), Vec2::new(1., 1.)),
+                (Vec2::new(-1., -1.), Vec2::new(-1., -1.)),
+                (Vec2::new(-1., -2.), Vec2::new(-1., -1.)),
+                (Vec2::new(-1., -3.), Vec2::new(-1., -1.)),
+                (Vec2::new(-1., 1.), Vec2::new(-1., 1.)),
+                (Vec2::new(1., -1.), Vec2::new(1., -1.)),
+            ] {
+                dda_line(&mut image, ip0 + o0, ip1 + o1, c0, c1);
+            }
+        }
+    }
+
+    image
+}
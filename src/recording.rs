@@ -0,0 +1,99 @@
+//! Recording a simulation as a sequence of images.
+//!
+//! A [`Recorder`] takes every nth rendered frame and writes it as a numbered PNG, or pipes the raw
+//! RGBA pixels into an encoder process such as ffmpeg. Frames are counted, not timed, so a
+//! recording shows the same steps however fast the frames were rendered.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use crate::raster::RgbaImage;
+
+enum Sink {
+    Png(PathBuf),
+    Encoder(Child),
+}
+
+pub struct Recorder {
+    pub width: u32,
+    pub height: u32,
+    /// One of this many rendered frames is captured.
+    pub every: usize,
+    /// Frames rendered since the recording started.
+    frames: usize,
+    captured: usize,
+    sink: Sink,
+}
+
+impl Recorder {
+    /// Writes the frames to `frame_00000.png`, `frame_00001.png` and so on in the directory,
+    /// which is created if needed.
+    pub fn to_directory(directory: impl AsRef<Path>, width: u32, height: u32, every: usize) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory.as_ref())?;
+        Ok(Self::new(Sink::Png(directory.as_ref().to_path_buf()), width, height, every))
+    }
+
+    /// Starts the encoder command and writes the raw RGBA frames to its standard input.
+    /// `{width}` and `{height}` in the command are replaced by the frame size, e.g.
+    /// `ffmpeg -f rawvideo -pix_fmt rgba -s {width}x{height} -i - simulation.mp4`.
+    pub fn to_encoder(command: &str, width: u32, height: u32, every: usize) -> std::io::Result<Self> {
+        let command = command.replace("{width}", &width.to_string()).replace("{height}", &height.to_string());
+        let mut args = command.split_whitespace();
+        let program = args.next().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "no encoder command given"))?;
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        Ok(Self::new(Sink::Encoder(child), width, height, every))
+    }
+
+    fn new(sink: Sink, width: u32, height: u32, every: usize) -> Self {
+        Self {
+            width,
+            height,
+            every: every.max(1),
+            frames: 0,
+            captured: 0,
+            sink,
+        }
+    }
+
+    /// Counts a rendered frame, returns whether it should be captured.
+    pub fn next_frame(&mut self) -> bool {
+        let capture = self.frames.is_multiple_of(self.every);
+        self.frames += 1;
+        capture
+    }
+
+    /// Frames written so far.
+    pub fn captured(&self) -> usize {
+        self.captured
+    }
+
+    pub fn capture(&mut self, image: &RgbaImage) -> std::io::Result<()> {
+        if (image.width, image.height) != (self.width, self.height) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("frame is {}x{}, the recording {}x{}", image.width, image.height, self.width, self.height)
+            ));
+        }
+
+        match &mut self.sink {
+            Sink::Png(directory) => image.save_png(directory.join(format!("frame_{:05}.png", self.captured)))?,
+            Sink::Encoder(child) => child.stdin.as_mut().unwrap().write_all(&image.pixels)?,
+        }
+        self.captured += 1;
+        Ok(())
+    }
+
+    /// Closes the input of the encoder and waits for it to finish writing.
+    pub fn finish(self) -> std::io::Result<()> {
+        let Sink::Encoder(mut child) = self.sink else { return Ok(()); };
+        drop(child.stdin.take());
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!("encoder exited with {}", status)));
+        }
+        Ok(())
+    }
+}
@@ -9,6 +9,27 @@ use cen::vulkan::{Buffer, CommandBuffer, DescriptorSetLayout, Image};
 use egui::debug_text::print;
 use glam::{IVec4, Mat4, Vec3, Vec4};
 use gpu_allocator::MemoryLocation;
+use crate::gpu_physics::PhysicsComponent;
+use crate::raster::RgbaImage;
+use crate::world::{EdgeId, NodeId, World};
+
+/// Value of ID buffer pixels that show neither a node nor an edge.
+const NO_ID: u32 = u32::MAX;
+/// Bits of an ID buffer pixel holding the node slot or edge entry, the bits above hold the depth.
+const ID_MASK: u32 = 0x1FFFFF;
+/// Set for edges, whose ids index the edge buffer rather than the node slots.
+const EDGE_BIT: u32 = 0x200000;
+
+/// Most node slots, and most entries of the edge buffer, the ID buffer tells apart. The edge
+/// buffer holds both directions of every edge, so this allows about half as many edges.
+pub const MAX_PICK_IDS: u32 = ID_MASK + 1;
+
+/// What is visible under a pixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Picked {
+    Node(NodeId),
+    Edge(EdgeId),
+}
 
 pub struct GraphRenderer {
     image: Option<Image>,
@@ -21,6 +42,14 @@ pub struct GraphRenderer {
     edge_descriptorset: Option<DescriptorSetLayout>,
     node_count: Option<u32>,
     edge_count: Option<u32>,
+    /// Write the nearest node or edge of every pixel to the ID buffer, needed by [`GraphRenderer::pick`].
+    /// Ignored while the graph has more ids than [`MAX_PICK_IDS`], see [`GraphRenderer::can_pick`].
+    pub picking: bool,
+    id_buffer: Option<Buffer>,
+    /// See [`PhysicsComponent::slot_generation`], for the buffers given to [`GraphRenderer::graph_data`].
+    slot_generation: u64,
+    /// Slot generation of the frame whose ids are in the ID buffer, `None` if it holds no ids.
+    picked_slots: Option<u64>,
 }
 
 #[derive(Copy)]
@@ -37,6 +66,7 @@ pub struct RenderNode {
 struct PushConstants {
     transform: Mat4,
     nodes: u32,
+    pick: u32,
 }
 
 impl GraphRenderer {
@@ -52,6 +82,10 @@ impl GraphRenderer {
             edge_pipeline: None,
             edge_descriptorset: None,
             transform: None,
+            picking: false,
+            id_buffer: None,
+            slot_generation: 0,
+            picked_slots: None,
         }
     }
 
@@ -59,24 +93,283 @@ impl GraphRenderer {
         self.transform = Some(transform);
     }
 
-    pub fn graph_data(&mut self, node_count: usize, buffer_info: DescriptorBufferInfo, edge_count: usize, edge_buffer_info: DescriptorBufferInfo) {
+    /// The buffers to draw, `slot_generation` is [`PhysicsComponent::slot_generation`] of their layout.
+    pub fn graph_data(&mut self, node_count: usize, buffer_info: DescriptorBufferInfo, edge_count: usize, edge_buffer_info: DescriptorBufferInfo, slot_generation: u64) {
 
-        self.node_count = Some(node_count as u32);
+        self.slot_generation = slot_generation;
+        self.node_count = Some(node_count.try_into().unwrap_or(u32::MAX));
         self.buffer_info = Some(buffer_info);
-        self.edge_count = Some(edge_count as u32);
+        self.edge_count = Some(edge_count.try_into().unwrap_or(u32::MAX));
         self.edge_buffer_info = Some(edge_buffer_info);
     }
-}
 
-impl RenderComponent for GraphRenderer {
-    fn initialize(&mut self, renderer: &mut Renderer) {
+    /// Whether [`GraphRenderer::picking`] is on and every node slot and edge entry fits in the id
+    /// bits of the ID buffer. Larger graphs are not picked rather than picked wrong.
+    pub fn can_pick(&self) -> bool {
+        self.picking
+            && self.node_count.unwrap_or(0) <= MAX_PICK_IDS
+            && self.edge_count.unwrap_or(0) <= MAX_PICK_IDS
+    }
+
+    /// Records drawing the nodes and the edges into `image`, which is in the general layout.
+    fn record_graph(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, image: &Image, pick: bool) {
+        // Clear render image
+        unsafe {
+            renderer.device.handle().cmd_clear_color_image(
+                command_buffer.handle(),
+                *image.handle(),
+                vk::ImageLayout::GENERAL,
+                &vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 1.0]
+                },
+                &[vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }]
+            );
+        }
+        renderer.transition_image(
+            command_buffer,
+            image.handle(),
+            vk::ImageLayout::GENERAL,
+            vk::ImageLayout::GENERAL,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::SHADER_WRITE
+        );
+
+        // Clear the ids of the previous frame
+        let id_buffer = self.id_buffer.as_ref().unwrap();
+        if pick {
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::DependencyFlags::empty(),
+                vk::WHOLE_SIZE,
+                0,
+                id_buffer
+            );
+            unsafe {
+                renderer.device.handle().cmd_fill_buffer(command_buffer.handle(), id_buffer.binding().buffer, 0, vk::WHOLE_SIZE, NO_ID);
+            }
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                vk::DependencyFlags::empty(),
+                vk::WHOLE_SIZE,
+                0,
+                id_buffer
+            );
+        }
+        let id_buffer_bindings = [id_buffer.binding()];
+
+        // Render
+        let compute = renderer.pipeline_store().get(self.pipeline.unwrap()).unwrap();
+
+        // Create push constant
+        let push_constants = if let Some(transform) = self.transform {
+            PushConstants {
+                transform,
+                nodes: self.node_count.unwrap(),
+                pick: pick as u32,
+            }
+        } else {
+            panic!("No transform provided");
+        };
+
+        command_buffer.bind_pipeline(&compute);
+
+        let image_bindings = [image.binding(vk::ImageLayout::GENERAL)];
+        let image_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+            .image_info(&image_bindings);
+
+        let buffer_bindings = [self.buffer_info.unwrap()];
+        let buffer_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(1)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&buffer_bindings);
+
+        let id_buffer_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(2)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&id_buffer_bindings);
+
+        command_buffer.bind_push_descriptor(
+            &compute,
+            0,
+            &[image_write_descriptor_set, buffer_write_descriptor_set, id_buffer_write_descriptor_set]
+        );
+
+        command_buffer.push_constants(
+            &compute,
+            ShaderStageFlags::COMPUTE,
+            0,
+            bytemuck::bytes_of(&push_constants)
+        );
+
+        let dispatches = self.node_count.unwrap().div_ceil(16);
+        command_buffer.dispatch(dispatches, 1, 1 );
+
+        // Render edges
+        let compute = renderer.pipeline_store().get(self.edge_pipeline.unwrap()).unwrap();
+
+        command_buffer.bind_pipeline(&compute);
+
+        let edge_buffer_bindings = [self.edge_buffer_info.unwrap()];
+        let edge_buffer_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(2)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&edge_buffer_bindings);
+
+        // Edges test their depth against the nodes drawn above
+        if pick {
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                vk::DependencyFlags::empty(),
+                vk::WHOLE_SIZE,
+                0,
+                id_buffer
+            );
+        }
+
+        let id_buffer_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(3)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&id_buffer_bindings);
+
+        command_buffer.bind_push_descriptor(
+            &compute,
+            0,
+            &[image_write_descriptor_set, buffer_write_descriptor_set, edge_buffer_write_descriptor_set, id_buffer_write_descriptor_set]
+        );
+
+        let push_constants = if let Some(transform) = self.transform {
+            PushConstants {
+                transform,
+                nodes: self.edge_count.unwrap(),
+                pick: pick as u32,
+            }
+        } else {
+            panic!("No transform provided");
+        };
+
+        command_buffer.push_constants(
+            &compute,
+            ShaderStageFlags::COMPUTE,
+            0,
+            bytemuck::bytes_of(&push_constants)
+        );
+
+        let dispatches = self.edge_count.unwrap().div_ceil(16);
+        command_buffer.dispatch(dispatches, 1, 1 );
+
+        // Make the ids visible to pick
+        if pick {
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::HOST,
+                vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::HOST_READ,
+                vk::DependencyFlags::empty(),
+                vk::WHOLE_SIZE,
+                0,
+                id_buffer
+            );
+        }
+    }
+
+    /// Renders the graph into an image of its own with the given size and reads it back, waits
+    /// until the GPU is idle. Nothing is written to the ID buffer.
+    pub fn render_offscreen(&mut self, renderer: &mut Renderer, width: u32, height: u32) -> RgbaImage {
+        unsafe { renderer.device.handle().device_wait_idle().unwrap(); }
+
+        let image = Image::new(
+            &renderer.device,
+            &mut renderer.allocator,
+            width,
+            height,
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST
+        );
+        let mut readback = Buffer::new(
+            &renderer.device,
+            &mut renderer.allocator,
+            MemoryLocation::GpuToCpu,
+            (width as usize * height as usize * 4) as DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_DST
+        );
+
+        let mut command_buffer = CommandBuffer::new(&renderer.device, &renderer.command_pool, false);
+        command_buffer.begin();
+        renderer.transition_image(&command_buffer, image.handle(), vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL, vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER, vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE);
+        self.record_graph(renderer, &mut command_buffer, &image, false);
+        renderer.transition_image(&command_buffer, image.handle(), vk::ImageLayout::GENERAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::TRANSFER, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::TRANSFER_READ);
+        unsafe {
+            renderer.device.handle().cmd_copy_image_to_buffer(
+                command_buffer.handle(),
+                *image.handle(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback.binding().buffer,
+                &[vk::BufferImageCopy::default()
+                    .image_subresource(
+                        vk::ImageSubresourceLayers::default()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_array_layer(0)
+                            .layer_count(1)
+                            .mip_level(0)
+                    )
+                    .image_extent(vk::Extent3D { width, height, depth: 1 })
+                ]
+            );
+        }
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::HOST_READ,
+            vk::DependencyFlags::empty(),
+            vk::WHOLE_SIZE,
+            0,
+            &readback
+        );
+        command_buffer.end();
+        renderer.device.submit_single_time_command(renderer.queue, &command_buffer);
+        unsafe { renderer.device.handle().device_wait_idle().unwrap(); }
+
+        // The render image is rgba8, like the pixels of a PNG
+        let guard = readback.mapped().unwrap();
+        RgbaImage {
+            width,
+            height,
+            pixels: guard.as_slice().to_vec(),
+        }
+    }
 
+    /// The render image, in the general layout, and an ID buffer of the same size.
+    fn create_targets(renderer: &mut Renderer, width: u32, height: u32) -> (Image, Buffer) {
         // Image
         let image = Image::new(
             &renderer.device,
             &mut renderer.allocator,
-            renderer.swapchain.get_extent().width,
-            renderer.swapchain.get_extent().height,
+            width,
+            height,
             vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST
         );
 
@@ -89,6 +382,58 @@ impl RenderComponent for GraphRenderer {
         image_command_buffer.end();
         renderer.device.submit_single_time_command(renderer.queue, &image_command_buffer);
 
+        // One id per pixel, read on the host when picking
+        let id_buffer = Buffer::new(
+            &renderer.device,
+            &mut renderer.allocator,
+            MemoryLocation::GpuToCpu,
+            (width as usize * height as usize * size_of::<u32>()) as DeviceSize,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST
+        );
+
+        (image, id_buffer)
+    }
+
+    /// Whether the ID buffer holds the ids of the last frame and the slots did not move since, so
+    /// they still name the same nodes and edges.
+    pub fn has_ids(&self, physics: &PhysicsComponent) -> bool {
+        self.can_pick() && self.picked_slots == Some(physics.slot_generation())
+    }
+
+    /// The node or edge drawn nearest to the camera at pixel `x`, `y` of the last frame.
+    ///
+    /// Waits until the GPU is idle. Nothing is picked unless [`GraphRenderer::has_ids`].
+    pub fn pick(&mut self, renderer: &Renderer, world: &World, physics: &PhysicsComponent, x: u32, y: u32) -> Option<Picked> {
+        let width = self.image.as_ref()?.width;
+        let height = self.image.as_ref()?.height;
+        if !self.has_ids(physics) || x >= width || y >= height {
+            return None;
+        }
+
+        unsafe { renderer.device.handle().device_wait_idle().unwrap(); }
+
+        let mut guard = self.id_buffer.as_mut()?.mapped().unwrap();
+        let (_, ids, _) = unsafe { guard.as_mut_slice().align_to_mut::<u32>() };
+        let key = ids[(y * width + x) as usize];
+        if key == NO_ID {
+            return None;
+        }
+
+        let id = (key & ID_MASK) as usize;
+        if key & EDGE_BIT != 0 {
+            physics.edge_at(id).map(Picked::Edge)
+        } else {
+            world.node_at_slot(id).map(Picked::Node)
+        }
+    }
+}
+
+impl RenderComponent for GraphRenderer {
+    fn initialize(&mut self, renderer: &mut Renderer) {
+
+        let extent = renderer.swapchain.get_extent();
+        let (image, id_buffer) = Self::create_targets(renderer, extent.width, extent.height);
+
         // Layout
         let layout_bindings = &[
             vk::DescriptorSetLayoutBinding::default()
@@ -101,6 +446,11 @@ impl RenderComponent for GraphRenderer {
                 .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
                 .descriptor_count(1)
                 .stage_flags(vk::ShaderStageFlags::COMPUTE ),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(2)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
         ];
         let descriptorset = DescriptorSetLayout::new_push_descriptor(
             &renderer.device,
@@ -141,6 +491,11 @@ impl RenderComponent for GraphRenderer {
                 .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
                 .descriptor_count(1)
                 .stage_flags(vk::ShaderStageFlags::COMPUTE ),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(3)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
         ];
         let edge_descriptorset = DescriptorSetLayout::new_push_descriptor(
             &renderer.device,
@@ -160,6 +515,7 @@ impl RenderComponent for GraphRenderer {
         }).expect("Failed to create pipeline");
 
         self.image = Some(image);
+        self.id_buffer = Some(id_buffer);
         self.descriptorset = Some(descriptorset);
         self.pipeline = Some(pipeline);
         self.edge_pipeline = Some(edge_pipeline);
@@ -167,107 +523,21 @@ impl RenderComponent for GraphRenderer {
     }
 
     fn render(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, swapchain_image: &vk::Image, _: &vk::ImageView) {
-
-        // Clear render image
-        unsafe {
-            renderer.device.handle().cmd_clear_color_image(
-                command_buffer.handle(),
-                *self.image.as_ref().unwrap().handle(),
-                vk::ImageLayout::GENERAL,
-                &vk::ClearColorValue {
-                    float32: [0.0, 0.0, 0.0, 1.0]
-                },
-                &[vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: 1,
-                    base_array_layer: 0,
-                    layer_count: 1,
-                }]
-            );
+        // Follow the size of the window, so the ids stay under the pixels they were drawn at
+        let extent = renderer.swapchain.get_extent();
+        let resized = self.image.as_ref().is_some_and(|image| (image.width, image.height) != (extent.width, extent.height));
+        if resized && extent.width > 0 && extent.height > 0 {
+            unsafe { renderer.device.handle().device_wait_idle().unwrap(); }
+            let (image, id_buffer) = Self::create_targets(renderer, extent.width, extent.height);
+            self.image = Some(image);
+            self.id_buffer = Some(id_buffer);
         }
 
-        // Render
-        let compute = renderer.pipeline_store().get(self.pipeline.unwrap()).unwrap();
-
-        // Create push constant
-        let push_constants = if let Some(transform) = self.transform {
-            PushConstants {
-                transform,
-                nodes: self.node_count.unwrap()
-            }
-        } else {
-            panic!("No transform provided");
-        };
-
-        command_buffer.bind_pipeline(&compute);
-
-        let image_bindings = [self.image.as_ref().unwrap().binding(vk::ImageLayout::GENERAL)];
-        let image_write_descriptor_set = WriteDescriptorSet::default()
-            .dst_binding(0)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
-            .image_info(&image_bindings);
-
-        let buffer_bindings = [self.buffer_info.unwrap()];
-        let buffer_write_descriptor_set = WriteDescriptorSet::default()
-            .dst_binding(1)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-            .buffer_info(&buffer_bindings);
-
-        command_buffer.bind_push_descriptor(
-            &compute,
-            0,
-            &[image_write_descriptor_set, buffer_write_descriptor_set]
-        );
-
-        command_buffer.push_constants(
-            &compute,
-            ShaderStageFlags::COMPUTE,
-            0,
-            bytemuck::bytes_of(&push_constants)
-        );
-
-        let dispatches = self.node_count.unwrap().div_ceil(16);
-        command_buffer.dispatch(dispatches, 1, 1 );
-
-        // Render edges
-        let compute = renderer.pipeline_store().get(self.edge_pipeline.unwrap()).unwrap();
-
-        command_buffer.bind_pipeline(&compute);
-
-        let edge_buffer_bindings = [self.edge_buffer_info.unwrap()];
-        let edge_buffer_write_descriptor_set = WriteDescriptorSet::default()
-            .dst_binding(2)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-            .buffer_info(&edge_buffer_bindings);
-
-        command_buffer.bind_push_descriptor(
-            &compute,
-            0,
-            &[image_write_descriptor_set, buffer_write_descriptor_set, edge_buffer_write_descriptor_set]
-        );
-
-        let push_constants = if let Some(transform) = self.transform {
-            PushConstants {
-                transform,
-                nodes: self.edge_count.unwrap()
-            }
-        } else {
-            panic!("No transform provided");
-        };
-
-        command_buffer.push_constants(
-            &compute,
-            ShaderStageFlags::COMPUTE,
-            0,
-            bytemuck::bytes_of(&push_constants)
-        );
-
-        let dispatches = self.edge_count.unwrap().div_ceil(16);
-        command_buffer.dispatch(dispatches, 1, 1 );
+        let pick = self.can_pick();
+        let image = self.image.take().unwrap();
+        self.record_graph(renderer, command_buffer, &image, pick);
+        self.image = Some(image);
+        self.picked_slots = pick.then_some(self.slot_generation);
 
         // Transition the render to a source
         renderer.transition_image(
@@ -0,0 +1,102 @@
+use slotmap::SecondaryMap;
+use crate::world::NodeId;
+
+/// How a set of picked nodes is combined with the current selection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// The picked nodes become the selection.
+    Replace,
+    Add,
+    /// Picked nodes are selected if they weren't and deselected if they were.
+    Toggle,
+}
+
+/// Set of selected nodes that remembers the order in which they were selected. Removing a node
+/// moves the most recently selected one into its place, so removals take constant time.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    order: Vec<NodeId>,
+    /// Index of every selected node in `order`.
+    members: SecondaryMap<NodeId, usize>,
+}
+
+impl Selection {
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.members.contains_key(id)
+    }
+
+    /// Adds the node, returns false if it was already selected.
+    pub fn insert(&mut self, id: NodeId) -> bool {
+        if self.members.contains_key(id) {
+            return false;
+        }
+        self.members.insert(id, self.order.len());
+        self.order.push(id);
+        true
+    }
+
+    /// Removes the node, returns false if it wasn't selected.
+    pub fn remove(&mut self, id: NodeId) -> bool {
+        let Some(index) = self.members.remove(id) else { return false; };
+        self.order.swap_remove(index);
+        if let Some(moved) = self.order.get(index) {
+            self.members[*moved] = index;
+        }
+        true
+    }
+
+    pub fn toggle(&mut self, id: NodeId) {
+        if !self.remove(id) {
+            self.insert(id);
+        }
+    }
+
+    pub fn combine(&mut self, ids: impl IntoIterator<Item = NodeId>, mode: SelectionMode) {
+        if mode == SelectionMode::Replace {
+            self.clear();
+        }
+        for id in ids {
+            match mode {
+                SelectionMode::Toggle => self.toggle(id),
+                SelectionMode::Replace | SelectionMode::Add => {
+                    self.insert(id);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.members.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// The node that was selected first, unless it was moved by a removal.
+    pub fn first(&self) -> Option<NodeId> {
+        self.order.first().copied()
+    }
+
+    /// Selected nodes in the order described on [`Selection`].
+    pub fn iter(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.order.iter().copied()
+    }
+
+    pub fn as_slice(&self) -> &[NodeId] {
+        &self.order
+    }
+}
+
+impl Extend<NodeId> for Selection {
+    fn extend<T: IntoIterator<Item = NodeId>>(&mut self, ids: T) {
+        for id in ids {
+            self.insert(id);
+        }
+    }
+}
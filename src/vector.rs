@@ -0,0 +1,224 @@
+//! Vector images of the graph for print, as SVG or PDF.
+//!
+//! [`vectorize`] projects the world with the transform of the renderer and turns the rings and
+//! lines of the shaders into circles and line segments, sorted back to front. Node labels are
+//! written as text.
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use glam::{Mat4, Vec2, Vec3};
+use crate::raster::{density_color, project, relation_color, DENSITY_RINGS, NODE_RING, NODE_SIZE, SELECTION_COLOR, SELECTION_RINGS};
+use crate::world::World;
+
+/// Stroke width of the edges, about as wide as the lines of `edges.comp`.
+const LINE_WIDTH: f32 = 2.;
+
+/// Stroke width of the node rings.
+const RING_WIDTH: f32 = 1.;
+
+/// Control point distance of a cubic Bézier quarter circle, relative to the radius.
+const BEZIER_CIRCLE: f32 = 0.552_284_8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// A line fading from the colour at `from` to the colour at `to`.
+    Line { from: Vec2, to: Vec2, colors: [Vec3; 2] },
+    /// A ring, filled to hide what lies behind the node.
+    Circle { center: Vec2, radius: f32, stroke: Vec3, fill: Option<Vec3> },
+    Label { position: Vec2, size: f32, text: String },
+}
+
+/// Shapes in drawing order, in pixels with the origin at the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VectorImage {
+    pub width: u32,
+    pub height: u32,
+    pub shapes: Vec<Shape>,
+}
+
+/// Projects the world like [`rasterize`](crate::rasterize) does, farther nodes and edges first.
+pub fn vectorize(world: &World, transform: Mat4, width: u32, height: u32) -> VectorImage {
+    // Camera distance of every group of shapes, sorted at the end
+    let mut groups: Vec<(f32, Vec<Shape>)> = vec![];
+
+    for (id, edge) in world.edges() {
+        let source = world.node(edge.source).unwrap();
+        let target = world.node(edge.target).unwrap();
+        let (Some((p0, d0)), Some((p1, d1))) = (project(&transform, source.pos, width, height), project(&transform, target.pos, width, height)) else { continue; };
+
+        let base = relation_color(world.relation_index(id));
+        groups.push(((d0 + d1) / 2., vec![Shape::Line {
+            from: p0.truncate(),
+            to: p1.truncate(),
+            colors: [density_color(source.level, base), density_color(target.level, base)],
+        }]));
+    }
+
+    for (id, node) in world.nodes() {
+        let Some((pos, d)) = project(&transform, node.pos, width, height) else { continue; };
+        let center = pos.truncate();
+        let scale = 1. / d * NODE_SIZE;
+
+        let color = if world.is_selected(id) { SELECTION_COLOR } else { Vec3::ONE };
+        let mut shapes = vec![Shape::Circle { center, radius: NODE_RING * scale, stroke: color, fill: Some(Vec3::ZERO) }];
+        if node.level > 0. {
+            let density = density_color(node.level, relation_color(0));
+            shapes.extend(DENSITY_RINGS.map(|r| Shape::Circle { center, radius: r * scale, stroke: density, fill: None }));
+        }
+        if world.is_selected(id) {
+            shapes.extend(SELECTION_RINGS.map(|r| Shape::Circle { center, radius: r * scale, stroke: color, fill: None }));
+        }
+        if let Some(label) = world.label(id) {
+            shapes.push(Shape::Label {
+                position: center + Vec2::new(NODE_RING * scale + 2., scale / 3.),
+                size: scale,
+                text: label.to_string(),
+            });
+        }
+        groups.push((d, shapes));
+    }
+
+    // Stable, so nodes stay on top of edges at the same distance
+    groups.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    VectorImage {
+        width,
+        height,
+        shapes: groups.into_iter().flat_map(|(_, shapes)| shapes).collect(),
+    }
+}
+
+fn hex(color: Vec3) -> String {
+    let c = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.).round();
+    format!("#{:02x}{:02x}{:02x}", c.x as u8, c.y as u8, c.z as u8)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Text in a PDF string with the standard fonts, characters outside of ASCII become '?'.
+fn escape_pdf(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+impl VectorImage {
+    pub fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#, w = self.width, h = self.height).unwrap();
+        writeln!(svg, r#"<rect width="{}" height="{}" fill="black"/>"#, self.width, self.height).unwrap();
+
+        for (i, shape) in self.shapes.iter().enumerate() {
+            match shape {
+                Shape::Line { from, to, colors } => {
+                    let stroke = if colors[0] == colors[1] {
+                        hex(colors[0])
+                    } else {
+                        writeln!(
+                            svg,
+                            r#"<linearGradient id="g{i}" gradientUnits="userSpaceOnUse" x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}"><stop offset="0" stop-color="{}"/><stop offset="1" stop-color="{}"/></linearGradient>"#,
+                            from.x, from.y, to.x, to.y, hex(colors[0]), hex(colors[1])
+                        ).unwrap();
+                        format!("url(#g{})", i)
+                    };
+                    writeln!(svg, r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{}" stroke-width="{}"/>"#, from.x, from.y, to.x, to.y, stroke, LINE_WIDTH).unwrap();
+                }
+                Shape::Circle { center, radius, stroke, fill } => {
+                    let fill = fill.map(hex).unwrap_or("none".to_string());
+                    writeln!(svg, r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" stroke="{}" stroke-width="{}" fill="{}"/>"#, center.x, center.y, radius, hex(*stroke), RING_WIDTH, fill).unwrap();
+                }
+                Shape::Label { position, size, text } => {
+                    writeln!(svg, r#"<text x="{:.2}" y="{:.2}" font-family="sans-serif" font-size="{:.2}" fill="white">{}</text>"#, position.x, position.y, size, escape_xml(text)).unwrap();
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Page content in PDF operators. Strokes have a single colour here, lines take the mean of
+    /// their end colours.
+    fn pdf_content(&self) -> String {
+        let h = self.height as f32;
+        let mut content = String::new();
+        writeln!(content, "0 0 0 rg 0 0 {} {} re f", self.width, self.height).unwrap();
+
+        for shape in self.shapes.iter() {
+            match shape {
+                Shape::Line { from, to, colors } => {
+                    let c = (colors[0] + colors[1]) / 2.;
+                    writeln!(content, "{:.3} {:.3} {:.3} RG {} w {:.2} {:.2} m {:.2} {:.2} l S", c.x, c.y, c.z, LINE_WIDTH, from.x, h - from.y, to.x, h - to.y).unwrap();
+                }
+                Shape::Circle { center, radius, stroke, fill } => {
+                    let (x, y, r) = (center.x, h - center.y, *radius);
+                    let k = r * BEZIER_CIRCLE;
+                    write!(content, "{:.3} {:.3} {:.3} RG {} w ", stroke.x, stroke.y, stroke.z, RING_WIDTH).unwrap();
+                    if let Some(fill) = fill {
+                        write!(content, "{:.3} {:.3} {:.3} rg ", fill.x, fill.y, fill.z).unwrap();
+                    }
+                    write!(content, "{:.2} {:.2} m ", x + r, y).unwrap();
+                    write!(content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c ", x + r, y + k, x + k, y + r, x, y + r).unwrap();
+                    write!(content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c ", x - k, y + r, x - r, y + k, x - r, y).unwrap();
+                    write!(content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c ", x - r, y - k, x - k, y - r, x, y - r).unwrap();
+                    write!(content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c ", x + k, y - r, x + r, y - k, x + r, y).unwrap();
+                    writeln!(content, "{}", if fill.is_some() { "b" } else { "s" }).unwrap();
+                }
+                Shape::Label { position, size, text } => {
+                    writeln!(content, "BT 1 1 1 rg /F1 {:.2} Tf {:.2} {:.2} Td ({}) Tj ET", size, position.x, h - position.y, escape_pdf(text)).unwrap();
+                }
+            }
+        }
+        content
+    }
+
+    /// A single page PDF with the image size in points, labels use Helvetica.
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let content = self.pdf_content();
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>",
+                self.width, self.height
+            ),
+            format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+        }
+
+        let xref = pdf.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            writeln!(table, "{:010} 00000 n ", offset).unwrap();
+        }
+        write!(table, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).unwrap();
+        pdf.extend_from_slice(table.as_bytes());
+        pdf
+    }
+
+    /// Writes an SVG or a PDF, depending on the extension of the path.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let data = match path.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("svg") => self.to_svg().into_bytes(),
+            Some("pdf") => self.to_pdf(),
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("no vector format for {}", path.display()))),
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&data)?;
+        writer.flush()
+    }
+}
@@ -1,129 +1,435 @@
-use std::ops::Index;
-use std::time::Instant;
+use std::collections::BTreeSet;
 use glam::Vec3;
-use petgraph::{Directed, Direction};
-use petgraph::data::Build;
-use petgraph::graph::{DiGraph, Edge, Edges, NodeIndex, NodeWeightsMut, UnGraph};
-use petgraph::prelude::EdgeRef;
-use petgraph::visit::{IntoEdges, IntoEdgesDirected, NodeCount};
-use rand::random;
+use rand::{random, Rng};
+use slotmap::{new_key_type, Key, SecondaryMap, SlotMap};
+use crate::attributes::{EdgeAttributes, NodeAttributes, Properties, Value};
+use crate::selection::{Selection, SelectionMode};
+
+new_key_type! {
+    /// Stable handle to a node in a [`World`], stays valid when other nodes are removed.
+    pub struct NodeId;
+
+    /// Stable handle to an edge in a [`World`], stays valid when other edges are removed.
+    pub struct EdgeId;
+}
 
 #[derive(Default)]
 #[derive(Copy)]
 #[derive(Clone)]
 pub struct Node {
     pub pos: Vec3,
-    pub level: f32
+    /// Velocity of the simulated layout, only known after reading the layout back from the GPU.
+    pub vel: Vec3,
+    pub level: f32,
+    /// Pinned nodes keep their position, the simulation does not move them.
+    pub pinned: bool,
 }
 
 impl Node {
     pub fn new_random(level: f32) -> Node {
         Node {
             pos: Vec3::new(random::<f32>() - 0.5, random::<f32>() - 0.5, random::<f32>() - 0.5) * 0.3,
-            level
+            vel: Vec3::ZERO,
+            level,
+            pinned: false,
         }
     }
 
     pub fn new(pos: Vec3, level: f32) -> Node {
         Node {
             pos,
-            level
+            vel: Vec3::ZERO,
+            level,
+            pinned: false,
+        }
+    }
+}
+
+#[derive(Copy)]
+#[derive(Clone)]
+pub struct Edge {
+    pub source: NodeId,
+    pub target: NodeId,
+    /// Scales the spring strength of the edge.
+    pub weight: f32,
+}
+
+/// Maps stable keys onto a dense range of slots `0..len`.
+///
+/// The GPU buffers are indexed by slot. Removing a key moves the key in the last slot into the
+/// freed one, so the range stays dense while the keys themselves never change.
+pub struct DenseSlots<K: Key> {
+    slots: SecondaryMap<K, usize>,
+    keys: Vec<K>,
+}
+
+impl<K: Key> Default for DenseSlots<K> {
+    fn default() -> Self {
+        Self {
+            slots: SecondaryMap::new(),
+            keys: vec![],
         }
     }
 }
 
-pub(crate) struct World {
-    center_attraction: f32,
-    edge_strength: f32,
-    graph: DiGraph<Node, ()>,
-    bh_physics: bool,
-    bh_theta: f32,
+impl<K: Key> DenseSlots<K> {
+    /// Appends the key and returns its slot.
+    pub fn insert(&mut self, key: K) -> usize {
+        let slot = self.keys.len();
+        self.slots.insert(key, slot);
+        self.keys.push(key);
+        slot
+    }
+
+    /// Removes the key and returns the slot it occupied. The key that was stored in the last slot
+    /// now lives in the returned slot, unless the removed key was the last one.
+    pub fn remove(&mut self, key: K) -> Option<usize> {
+        let slot = self.slots.remove(key)?;
+        self.keys.swap_remove(slot);
+        if let Some(moved) = self.keys.get(slot) {
+            self.slots[*moved] = slot;
+        }
+        Some(slot)
+    }
+
+    pub fn slot(&self, key: K) -> Option<usize> {
+        self.slots.get(key).copied()
+    }
+
+    pub fn key(&self, slot: usize) -> Option<K> {
+        self.keys.get(slot).copied()
+    }
+
+    /// All keys, ordered by slot.
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.keys.clear();
+    }
+}
+
+/// Edits made to a [`World`] that have not been uploaded to the GPU yet.
+#[derive(Default)]
+pub struct Changes {
+    /// Slot moves caused by node removals as `(from, to)`, in the order they happened.
+    pub moved: Vec<(usize, usize)>,
+    /// Slots of nodes whose data was written on the CPU.
+    pub nodes: BTreeSet<usize>,
+    /// The edge list has to be rebuilt.
+    pub edges: bool,
+    /// The selection changed, so the highlight of every node has to be updated.
+    pub selection: bool,
+    /// Nodes were pinned or unpinned, without changing their position.
+    pub pinned: bool,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.moved.is_empty() && self.nodes.is_empty() && !self.edges && !self.selection && !self.pinned
+    }
+
+    /// Changes that cause every node and edge of the world to be uploaded.
+    pub fn everything(world: &World) -> Changes {
+        Changes {
+            moved: vec![],
+            nodes: (0..world.node_count()).collect(),
+            edges: true,
+            selection: true,
+            pinned: true,
+        }
+    }
+}
+
+pub struct World {
+    nodes: SlotMap<NodeId, Node>,
+    node_attributes: SecondaryMap<NodeId, NodeAttributes>,
+    edges: SlotMap<EdgeId, Edge>,
+    edge_attributes: SecondaryMap<EdgeId, EdgeAttributes>,
+    relations: Vec<String>,
+    node_slots: DenseSlots<NodeId>,
+    edge_slots: DenseSlots<EdgeId>,
+    outgoing: SecondaryMap<NodeId, Vec<EdgeId>>,
+    incoming: SecondaryMap<NodeId, Vec<EdgeId>>,
+    selection: Selection,
+    changes: Changes,
+    directed: bool,
     run_physics: bool,
 }
 
 impl World {
     pub fn new() -> Self {
 
-        let mut g = DiGraph::<Node, ()>::new();
+        let mut world = Self::empty();
 
         let nodes: usize = 400;
-        for i in 0..nodes {
-            g.add_node(Node::new_random(0.));
-        }
+        let ids = (0..nodes).map(|_| world.add_node(Node::new_random(0.))).collect::<Vec<_>>();
 
         for i in 0..( nodes as f32 * 1.04 ) as usize {
             let i_a = i % nodes;
             // let i_a = random::<usize>() % nodes;
             let i_b = random::<usize>() % nodes;
             if i_a == i_b { continue; }
-            g.add_edge(NodeIndex::new(i_a), NodeIndex::new(i_b), ());
-        }
-
-        // for x in 0..100 {
-        //     for y in 0..10 {
-        //         let mut index = g.node_count();
-        //         g.add_node(Node::new(Vec3::new(x as f32, y as f32, -0.5) / 10. + Vec3::new(-0.5, -0.5, 0.), 0));
-        //
-        //         for z in 0..9 {
-        //
-        //             g.add_node(Node::new(Vec3::new(x as f32, y as f32, z as f32 - 5.) / 10. + Vec3::new(-0.5, -0.5, 0.1), z + 1));
-        //
-        //             // Add an edge to the child
-        //             let id_a = g.node_indices().nth(index).unwrap();
-        //             let id_b = g.node_indices().nth(index + 1).unwrap();
-        //             g.update_edge(id_a, id_b, ());
-        //             index += 1;
-        //         }
-        //     }
-        // }
+            world.add_edge(ids[i_a], ids[i_b]);
+        }
+
+        world
+    }
 
+    /// Creates a world without any nodes or edges.
+    pub fn empty() -> Self {
         Self {
-            edge_strength: 20.0,
-            center_attraction: 20000.0,
-            graph: g,
-            bh_physics: false,
-            bh_theta: 0.5,
+            nodes: SlotMap::with_key(),
+            node_attributes: SecondaryMap::new(),
+            edges: SlotMap::with_key(),
+            edge_attributes: SecondaryMap::new(),
+            relations: vec![],
+            node_slots: DenseSlots::default(),
+            edge_slots: DenseSlots::default(),
+            outgoing: SecondaryMap::new(),
+            incoming: SecondaryMap::new(),
+            selection: Selection::default(),
+            changes: Changes::default(),
+            directed: true,
             run_physics: true
         }
     }
 
-    pub fn bh_physics(&mut self) -> &mut bool {
-        &mut self.bh_physics
+    pub fn add_node(&mut self, node: Node) -> NodeId {
+        self.add_node_with(node, NodeAttributes::default())
     }
 
-    pub fn run_physics(&mut self) -> &mut bool {
-        &mut self.run_physics
+    pub fn add_node_with(&mut self, node: Node, attributes: NodeAttributes) -> NodeId {
+        let id = self.nodes.insert(node);
+        self.node_attributes.insert(id, attributes);
+        let slot = self.node_slots.insert(id);
+        self.outgoing.insert(id, vec![]);
+        self.incoming.insert(id, vec![]);
+        self.changes.nodes.insert(slot);
+        id
     }
 
-    pub fn get_bh_theta(&mut self) -> &mut f32 {
-        &mut self.bh_theta
+    /// Removes the node together with all edges connected to it.
+    pub fn remove_node(&mut self, id: NodeId) -> Option<Node> {
+        if !self.nodes.contains_key(id) {
+            return None;
+        }
+
+        let connected = self.outgoing[id].iter().chain(self.incoming[id].iter()).copied().collect::<Vec<_>>();
+        for edge in connected {
+            self.remove_edge(edge);
+        }
+        self.outgoing.remove(id);
+        self.incoming.remove(id);
+        self.node_attributes.remove(id);
+        self.selection.remove(id);
+
+        // The node in the last slot is moved into the freed slot
+        let last = self.node_slots.len() - 1;
+        let slot = self.node_slots.remove(id).unwrap();
+        self.changes.nodes.remove(&slot);
+        if slot != last {
+            self.changes.moved.push((last, slot));
+            if self.changes.nodes.remove(&last) {
+                self.changes.nodes.insert(slot);
+            }
+        }
+        self.changes.edges = true;
+
+        self.nodes.remove(id)
+    }
+
+    /// Adds a directed edge with a weight of one, returns `None` if either of the nodes doesn't exist.
+    pub fn add_edge(&mut self, source: NodeId, target: NodeId) -> Option<EdgeId> {
+        self.add_edge_with(source, target, 1.0, EdgeAttributes::default())
+    }
+
+    pub fn add_edge_with(&mut self, source: NodeId, target: NodeId, weight: f32, attributes: EdgeAttributes) -> Option<EdgeId> {
+        if !self.nodes.contains_key(source) || !self.nodes.contains_key(target) {
+            return None;
+        }
+
+        if let Some(relation) = &attributes.relation {
+            self.intern_relation(relation);
+        }
+        let id = self.edges.insert(Edge { source, target, weight });
+        self.edge_attributes.insert(id, attributes);
+        self.edge_slots.insert(id);
+        self.outgoing[source].push(id);
+        self.incoming[target].push(id);
+        self.changes.edges = true;
+        Some(id)
+    }
+
+    pub fn remove_edge(&mut self, id: EdgeId) -> Option<Edge> {
+        let edge = self.edges.remove(id)?;
+        self.edge_attributes.remove(id);
+        self.edge_slots.remove(id);
+        self.outgoing[edge.source].retain(|e| *e != id);
+        self.incoming[edge.target].retain(|e| *e != id);
+        self.changes.edges = true;
+        Some(edge)
+    }
+
+    /// Returns an edge going from `source` to `target`, if there is one.
+    pub fn find_edge(&self, source: NodeId, target: NodeId) -> Option<EdgeId> {
+        self.outgoing(source).iter().copied().find(|e| self.edges[*e].target == target)
     }
 
-    pub fn get_center_attraction_mut(&mut self) -> &mut f32 {
-        &mut self.center_attraction
+    pub fn set_position(&mut self, id: NodeId, pos: Vec3) {
+        if let Some(node) = self.nodes.get_mut(id) {
+            node.pos = pos;
+            node.vel = Vec3::ZERO;
+            self.changes.nodes.insert(self.node_slots.slot(id).unwrap());
+        }
+    }
+
+    /// Sets the level without marking the node as changed, the levels are uploaded on their own by
+    /// [`PhysicsComponent::update_weights`](crate::PhysicsComponent::update_weights).
+    pub fn set_level(&mut self, id: NodeId, level: f32) {
+        if let Some(node) = self.nodes.get_mut(id) {
+            node.level = level;
+        }
     }
 
-    pub fn get_edge_strength(&mut self) -> &mut f32 {
-        &mut self.edge_strength
+    pub fn is_pinned(&self, id: NodeId) -> bool {
+        self.nodes.get(id).is_some_and(|n| n.pinned)
+    }
+
+    /// Pins or unpins the node, its position on the GPU is left as it is.
+    pub fn set_pinned(&mut self, id: NodeId, pinned: bool) {
+        if let Some(node) = self.nodes.get_mut(id) {
+            node.pinned = pinned;
+            self.changes.pinned = true;
+        }
+    }
+
+    pub fn pin_selected(&mut self, pinned: bool) {
+        let ids = self.selection.as_slice().to_vec();
+        for id in ids {
+            self.set_pinned(id, pinned);
+        }
+    }
+
+    /// Takes over positions and velocities computed by the simulation in slot order, without
+    /// marking the nodes as changed.
+    pub fn apply_layout(&mut self, layout: impl IntoIterator<Item = (Vec3, Vec3)>) {
+        for (slot, (pos, vel)) in layout.into_iter().enumerate() {
+            let Some(id) = self.node_slots.key(slot) else { break; };
+            self.nodes[id].pos = pos;
+            self.nodes[id].vel = vel;
+        }
+    }
+
+    /// Removes all nodes and edges.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.node_attributes.clear();
+        self.edges.clear();
+        self.edge_attributes.clear();
+        self.relations.clear();
+        self.node_slots.clear();
+        self.edge_slots.clear();
+        self.outgoing.clear();
+        self.incoming.clear();
+        self.selection.clear();
+        self.changes = Changes {
+            edges: true,
+            ..Changes::default()
+        };
+    }
+
+    /// Moves every node to a random position.
+    pub fn randomize(&mut self) {
+        self.randomize_with(&mut rand::thread_rng());
+    }
+
+    /// Like [`World::randomize`], with the positions drawn from `rng` so they can be reproduced.
+    pub fn randomize_with(&mut self, rng: &mut impl Rng) {
+        for id in self.node_slots.keys() {
+            let node = &mut self.nodes[*id];
+            node.pos = (Vec3::new(rng.gen(), rng.gen(), rng.gen()) - 0.5) * 0.3;
+            node.vel = Vec3::ZERO;
+        }
+        self.changes.nodes.extend(0..self.node_slots.len());
+    }
+
+    /// Takes the edits made since the last call, used to update the GPU buffers incrementally.
+    pub fn take_changes(&mut self) -> Changes {
+        std::mem::take(&mut self.changes)
+    }
+
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    /// The selection for editing, marks it as changed so the highlight is updated.
+    pub fn selection_mut(&mut self) -> &mut Selection {
+        self.changes.selection = true;
+        &mut self.selection
+    }
+
+    pub fn is_selected(&self, id: NodeId) -> bool {
+        self.selection.contains(id)
+    }
+
+    pub fn select_all(&mut self) {
+        let ids = self.node_slots.keys().to_vec();
+        self.selection_mut().extend(ids);
+    }
+
+    pub fn invert_selection(&mut self) {
+        let ids = self.node_slots.keys().to_vec();
+        self.selection_mut().combine(ids, SelectionMode::Toggle);
+    }
+
+    /// Removes the selected nodes together with their edges.
+    pub fn remove_selected(&mut self) {
+        let ids = self.selection.as_slice().to_vec();
+        for id in ids {
+            self.remove_node(id);
+        }
+    }
+
+    /// Whether the edges have a direction. Edges are stored with a source and target either way.
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    pub fn set_directed(&mut self, directed: bool) {
+        self.directed = directed;
+    }
+
+    pub fn run_physics(&mut self) -> &mut bool {
+        &mut self.run_physics
     }
 
     pub fn update(&mut self) {
 
-        let g2 = self.graph.clone();
-        g2.node_indices().for_each(|i| {
-            let w = g2.node_weight(i).unwrap().level;
-            if w == 0.0 {
-                return;
+        let levels = self.nodes.iter().map(|(id, n)| (id, n.level)).collect::<SecondaryMap<NodeId, f32>>();
+        for &id in self.node_slots.keys() {
+            if levels[id] == 0.0 {
+                continue;
             }
 
-            let count = g2.edges_directed(i, Direction::Outgoing).count();
-            if count == 0 { return; }
+            let outgoing = &self.outgoing[id];
+            if outgoing.is_empty() { continue; }
 
-            g2.edges_directed(i, Direction::Outgoing).for_each(|edge| {
-                self.graph.node_weight_mut(edge.target()).unwrap().level = 1.;
-            });
-            self.graph.node_weight_mut(i).unwrap().level = 0.;
-        });
+            for edge in outgoing {
+                self.nodes[self.edges[*edge].target].level = 1.;
+            }
+            self.nodes[id].level = 0.;
+        }
 
         if !self.run_physics {
             return;
@@ -131,31 +437,215 @@ impl World {
 
     }
 
-    pub fn get_mesh(&mut self) -> (Vec<Node>, Vec<(usize, usize)>) {
-        let positions = self.graph.raw_nodes().iter().map(|n| n.weight).collect::<Vec<_>>();
-        let edges = self.graph.raw_edges().iter().map(|e| {
-            (e.source().index(), e.target().index())
-        }).collect::<Vec<(usize, usize)>>();
+    /// Returns the nodes and the edges as pairs of node slots, both ordered by slot.
+    pub fn get_mesh(&self) -> (Vec<Node>, Vec<(usize, usize)>) {
+        let positions = self.nodes().map(|(_, n)| *n).collect::<Vec<_>>();
+        let edges = self.dense_edges().collect::<Vec<(usize, usize)>>();
         (positions, edges)
     }
 
     pub fn node_count(&self) -> usize {
-        self.graph.node_count()
+        self.nodes.len()
     }
 
     pub fn edge_count(&self) -> usize {
-        self.graph.edge_count()
+        self.edges.len()
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    /// Marks the node as changed, so its position, velocity and pin are uploaded again on the next
+    /// sync. [`World::set_level`] changes the level alone.
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        let node = self.nodes.get_mut(id)?;
+        self.changes.nodes.insert(self.node_slots.slot(id).unwrap());
+        self.changes.pinned = true;
+        Some(node)
+    }
+
+    pub fn edge(&self, id: EdgeId) -> Option<&Edge> {
+        self.edges.get(id)
+    }
+
+    /// Iterates the nodes ordered by their dense slot.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> + '_ {
+        self.node_slots.keys().iter().map(|id| (*id, &self.nodes[*id]))
     }
 
-    pub fn nodes(&self) -> Vec<&Node> {
-        self.graph.node_weights().collect::<Vec<&Node>>()
+    /// Iterates the nodes mutably, in no particular order. Marks every node as changed, like
+    /// [`World::node_mut`].
+    pub fn nodes_mut(&mut self) -> impl Iterator<Item = (NodeId, &mut Node)> + '_ {
+        self.changes.nodes.extend(0..self.node_slots.len());
+        self.changes.pinned = true;
+        self.nodes.iter_mut()
     }
 
-    pub fn edges(&self) -> &[Edge<()>] {
-        self.graph.raw_edges()
+    /// Iterates the edges ordered by their dense slot.
+    pub fn edges(&self) -> impl Iterator<Item = (EdgeId, &Edge)> + '_ {
+        self.edge_slots.keys().iter().map(|id| (*id, &self.edges[*id]))
+    }
+
+    /// Iterates the edges as `(source slot, target slot)` pairs, ordered by edge slot.
+    pub fn dense_edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.edges().map(|(_, e)| {
+            (self.node_slots.slot(e.source).unwrap(), self.node_slots.slot(e.target).unwrap())
+        })
+    }
+
+    pub fn outgoing(&self, id: NodeId) -> &[EdgeId] {
+        self.outgoing.get(id).map(|e| e.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn incoming(&self, id: NodeId) -> &[EdgeId] {
+        self.incoming.get(id).map(|e| e.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn node_slot(&self, id: NodeId) -> Option<usize> {
+        self.node_slots.slot(id)
+    }
+
+    pub fn node_at_slot(&self, slot: usize) -> Option<NodeId> {
+        self.node_slots.key(slot)
+    }
+
+    pub fn edge_slot(&self, id: EdgeId) -> Option<usize> {
+        self.edge_slots.slot(id)
+    }
+
+    pub fn edge_at_slot(&self, slot: usize) -> Option<EdgeId> {
+        self.edge_slots.key(slot)
+    }
+
+    pub fn attributes(&self, id: NodeId) -> Option<&NodeAttributes> {
+        self.node_attributes.get(id)
+    }
+
+    pub fn attributes_mut(&mut self, id: NodeId) -> Option<&mut NodeAttributes> {
+        self.node_attributes.get_mut(id)
+    }
+
+    pub fn label(&self, id: NodeId) -> Option<&str> {
+        self.node_attributes.get(id)?.label.as_deref()
+    }
+
+    pub fn set_label(&mut self, id: NodeId, label: impl Into<String>) {
+        if let Some(attributes) = self.node_attributes.get_mut(id) {
+            attributes.label = Some(label.into());
+        }
     }
 
-    pub fn nodes_mut(&mut self) -> NodeWeightsMut<Node> {
-        self.graph.node_weights_mut()
+    pub fn kind(&self, id: NodeId) -> Option<&str> {
+        self.node_attributes.get(id)?.kind.as_deref()
     }
-}
\ No newline at end of file
+
+    pub fn set_kind(&mut self, id: NodeId, kind: impl Into<String>) {
+        if let Some(attributes) = self.node_attributes.get_mut(id) {
+            attributes.kind = Some(kind.into());
+        }
+    }
+
+    pub fn properties(&self, id: NodeId) -> Option<&Properties> {
+        Some(&self.node_attributes.get(id)?.properties)
+    }
+
+    pub fn property(&self, id: NodeId, key: &str) -> Option<&Value> {
+        self.node_attributes.get(id)?.properties.get(key)
+    }
+
+    /// Sets a property of the node, returning the previous value.
+    pub fn set_property(&mut self, id: NodeId, key: impl Into<String>, value: impl Into<Value>) -> Option<Value> {
+        self.node_attributes.get_mut(id)?.properties.insert(key.into(), value.into())
+    }
+
+    pub fn remove_property(&mut self, id: NodeId, key: &str) -> Option<Value> {
+        self.node_attributes.get_mut(id)?.properties.remove(key)
+    }
+
+    /// Returns the nodes whose attributes match the predicate, ordered by slot.
+    pub fn query(&self, predicate: impl Fn(&NodeAttributes) -> bool) -> Vec<NodeId> {
+        self.node_slots.keys().iter().copied().filter(|id| predicate(&self.node_attributes[*id])).collect()
+    }
+
+    /// Returns the nodes that have the property set to the given value.
+    pub fn nodes_with_property(&self, key: &str, value: &Value) -> Vec<NodeId> {
+        self.query(|a| a.properties.get(key) == Some(value))
+    }
+
+    pub fn nodes_of_kind(&self, kind: &str) -> Vec<NodeId> {
+        self.query(|a| a.kind.as_deref() == Some(kind))
+    }
+
+    pub fn nodes_in_group(&self, group: &str) -> Vec<NodeId> {
+        self.query(|a| a.groups.iter().any(|g| g == group))
+    }
+
+    /// All group names in order of first appearance.
+    pub fn groups(&self) -> Vec<&str> {
+        let mut groups: Vec<&str> = vec![];
+        for (id, _) in self.nodes() {
+            for group in &self.node_attributes[id].groups {
+                if !groups.contains(&group.as_str()) {
+                    groups.push(group);
+                }
+            }
+        }
+        groups
+    }
+
+    pub fn edge_attributes(&self, id: EdgeId) -> Option<&EdgeAttributes> {
+        self.edge_attributes.get(id)
+    }
+
+    /// Gives mutable access to the label and properties of an edge. The relation type is set
+    /// through [`World::set_relation`], so it can be registered for rendering.
+    pub fn edge_properties_mut(&mut self, id: EdgeId) -> Option<&mut Properties> {
+        Some(&mut self.edge_attributes.get_mut(id)?.properties)
+    }
+
+    pub fn set_edge_label(&mut self, id: EdgeId, label: impl Into<String>) {
+        if let Some(attributes) = self.edge_attributes.get_mut(id) {
+            attributes.label = Some(label.into());
+        }
+    }
+
+    pub fn set_weight(&mut self, id: EdgeId, weight: f32) {
+        if let Some(edge) = self.edges.get_mut(id) {
+            edge.weight = weight;
+            self.changes.edges = true;
+        }
+    }
+
+    pub fn relation(&self, id: EdgeId) -> Option<&str> {
+        self.edge_attributes.get(id)?.relation.as_deref()
+    }
+
+    pub fn set_relation(&mut self, id: EdgeId, relation: impl Into<String>) {
+        let relation = relation.into();
+        if self.edge_attributes.contains_key(id) {
+            self.intern_relation(&relation);
+            self.edge_attributes[id].relation = Some(relation);
+            self.changes.edges = true;
+        }
+    }
+
+    /// All relation types in use, in the order they were first seen.
+    pub fn relation_types(&self) -> &[String] {
+        &self.relations
+    }
+
+    /// Numeric relation type of an edge as used by the renderer, 0 for edges without a relation.
+    pub fn relation_index(&self, id: EdgeId) -> u32 {
+        self.relation(id)
+            .and_then(|r| self.relations.iter().position(|x| x == r))
+            .map(|i| i as u32 + 1)
+            .unwrap_or(0)
+    }
+
+    fn intern_relation(&mut self, relation: &str) {
+        if !self.relations.iter().any(|r| r == relation) {
+            self.relations.push(relation.to_string());
+        }
+    }
+}
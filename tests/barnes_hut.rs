@@ -0,0 +1,34 @@
+use glam::Vec3;
+use mycelia::barnes_hut::{repulsion, repulsion_exact};
+use mycelia::Node;
+
+fn positions(count: usize) -> Vec<Vec3> {
+    (0..count).map(|_| Node::new_random(0.).pos).collect()
+}
+
+#[test]
+fn zero_theta_is_exact() {
+    let positions = positions(500);
+    let exact = repulsion_exact(&positions, 1.2);
+    let approximate = repulsion(&positions, 1.2, 0.);
+    for (a, b) in exact.iter().zip(approximate.iter()) {
+        assert!((*a - *b).length() <= a.length() * 1e-4 + 1e-7, "{} != {}", a, b);
+    }
+}
+
+#[test]
+fn approximation_stays_close_to_the_exact_forces() {
+    let positions = positions(2000);
+    let exact = repulsion_exact(&positions, 1.2);
+    let approximate = repulsion(&positions, 1.2, 0.5);
+
+    let error = exact.iter().zip(approximate.iter()).map(|(a, b)| (*a - *b).length()).sum::<f32>();
+    let total = exact.iter().map(|a| a.length()).sum::<f32>();
+    assert!(error / total < 0.02, "relative error {}", error / total);
+}
+
+#[test]
+fn coincident_nodes_do_not_repel() {
+    let positions = vec![Vec3::ONE; 100];
+    assert!(repulsion(&positions, 1., 0.5).iter().all(|f| *f == Vec3::ZERO));
+}
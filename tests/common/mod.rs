@@ -0,0 +1,36 @@
+//! Helpers shared by the integration tests, each test crate uses a part of them.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use mycelia::{NodeId, World};
+
+pub fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+pub fn node_by_label(world: &World, label: &str) -> NodeId {
+    world.query(|a| a.label.as_deref() == Some(label))[0]
+}
+
+/// Checks that `reloaded` has the nodes and edges of `world`, matched by label, with the same
+/// attributes, weights and positions up to `tolerance`.
+pub fn assert_round_trip(world: &World, reloaded: &World, tolerance: f32) {
+    assert_eq!(reloaded.is_directed(), world.is_directed());
+    assert_eq!(reloaded.node_count(), world.node_count());
+    assert_eq!(reloaded.edge_count(), world.edge_count());
+
+    for (id, node) in world.nodes() {
+        let other = node_by_label(reloaded, world.label(id).unwrap());
+        let pos = reloaded.node(other).unwrap().pos;
+        assert!((pos - node.pos).length() <= tolerance, "{} moved from {} to {}", world.label(id).unwrap(), node.pos, pos);
+        assert_eq!(reloaded.attributes(other), world.attributes(id));
+    }
+
+    for (id, edge) in world.edges() {
+        let source = node_by_label(reloaded, world.label(edge.source).unwrap());
+        let target = node_by_label(reloaded, world.label(edge.target).unwrap());
+        let other = reloaded.find_edge(source, target).unwrap();
+        assert_eq!(reloaded.edge(other).unwrap().weight, edge.weight);
+        assert_eq!(reloaded.edge_attributes(other), world.edge_attributes(id));
+    }
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use mycelia::io::csv::CsvOptions;
+use mycelia::io::Error;
+use mycelia::{Value, World};
+use common::{fixture, node_by_label};
+
+#[test]
+fn reads_node_table_and_edge_list() {
+    let options = CsvOptions::default().label(Some("name"));
+    let nodes = std::fs::read_to_string(fixture("people.csv")).unwrap();
+    let edges = "source,target,weight,relation,since\nalice,bob,0.5,knows,2020\nalice,acme,2,works-at,\ncarol,acme,,,\n";
+    let world = World::read_csv(Some(&nodes), edges, &options).unwrap();
+
+    assert_eq!(world.node_count(), 4);
+    assert_eq!(world.edge_count(), 3);
+
+    // The label column names the node, the id column is kept as its id
+    let alice = node_by_label(&world, "Alice");
+    assert_eq!(world.attributes(alice).unwrap().id.as_deref(), Some("alice"));
+    assert_eq!(world.kind(alice), Some("person"));
+    assert_eq!(world.property(alice, "age"), Some(&Value::Number(34.)));
+    assert!(matches!(world.property(alice, "joined"), Some(Value::Timestamp(_))));
+
+    // Empty cells are skipped
+    let bob = node_by_label(&world, "Bob");
+    assert_eq!(world.property(bob, "age"), None);
+
+    let edge = world.find_edge(alice, bob).unwrap();
+    assert_eq!(world.edge(edge).unwrap().weight, 0.5);
+    assert_eq!(world.relation(edge), Some("knows"));
+    assert_eq!(world.edge_attributes(edge).unwrap().properties.get("since"), Some(&Value::Number(2020.)));
+
+    // Nodes only referenced by edges are created with their id as label
+    let carol = node_by_label(&world, "carol");
+    let acme = node_by_label(&world, "Acme, Inc.");
+    assert_eq!(world.edge(world.find_edge(carol, acme).unwrap()).unwrap().weight, 1.);
+}
+
+#[test]
+fn maps_columns_of_tab_separated_files() {
+    let options = CsvOptions::tsv()
+        .source("from")
+        .target("to")
+        .weight(Some("strength"))
+        .properties(["since"])
+        .directed(false);
+    let world = World::load_csv(None, &fixture("links.tsv"), &options).unwrap();
+
+    assert!(!world.is_directed());
+    assert_eq!(world.node_count(), 4);
+    assert_eq!(world.edge_count(), 3);
+
+    let alice = node_by_label(&world, "alice");
+    let bob = node_by_label(&world, "bob");
+    assert_eq!(world.attributes(alice).unwrap().id.as_deref(), Some("alice"));
+    let edge = world.find_edge(alice, bob).unwrap();
+    assert_eq!(world.edge(edge).unwrap().weight, 0.5);
+    assert_eq!(world.edge_attributes(edge).unwrap().properties.get("since"), Some(&Value::Number(2020.)));
+}
+
+#[test]
+fn reports_malformed_rows_with_line_numbers() {
+    let parse_line = |nodes: Option<&str>, edges: &str| match World::read_csv(nodes, edges, &CsvOptions::default()) {
+        Err(Error::Parse { line, .. }) => line,
+        _ => panic!("expected a parse error"),
+    };
+
+    assert_eq!(parse_line(None, "source,target,weight\na,b,1\nb,c,heavy\n"), 3);
+    assert_eq!(parse_line(None, "source,target\na,b\nb,c,d\n"), 3);
+    assert_eq!(parse_line(None, "source,target\na,b\n,c\n"), 3);
+    assert_eq!(parse_line(Some("id\na\na\n"), "source,target\n"), 3);
+
+    assert!(matches!(World::read_csv(None, "from,to\n", &CsvOptions::default()), Err(Error::Invalid(_))));
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use glam::Vec3;
+use mycelia::io::Error;
+use mycelia::{Color, Value, World};
+use common::{fixture, node_by_label};
+
+#[test]
+fn reads_statements_attributes_and_groups() {
+    let world = World::load_dot(fixture("dependencies.dot")).unwrap();
+
+    assert!(world.is_directed());
+    assert_eq!(world.node_count(), 6);
+    // The duplicate app -> core edge is dropped in a strict graph
+    assert_eq!(world.edge_count(), 4);
+
+    let app = node_by_label(&world, "app\nbinary");
+    assert_eq!(world.kind(app), Some("crate"));
+    assert_eq!(world.attributes(app).unwrap().color, Some(Color::new(255, 0, 0)));
+    assert_eq!(world.property(app, "shape"), Some(&Value::from("box")));
+    assert_eq!(world.node(app).unwrap().pos, Vec3::new(1., 2., 0.));
+
+    let core = node_by_label(&world, "core");
+    let macros = node_by_label(&world, "core-macros");
+    assert_eq!(world.kind(macros), Some("proc-macro"));
+    assert_eq!(world.nodes_in_group("cluster_core"), vec![core, macros]);
+    assert_eq!(world.groups(), vec!["cluster_core", "tools"]);
+
+    let uses = world.find_edge(core, macros).unwrap();
+    assert_eq!(world.relation(uses), Some("uses"));
+    assert_eq!(world.edge_attributes(uses).unwrap().color, Some(Color::new(128, 128, 128)));
+
+    let lint = node_by_label(&world, "lint");
+    let edge = world.find_edge(app, lint).unwrap();
+    assert_eq!(world.edge(edge).unwrap().weight, 3.);
+    assert_eq!(world.edge_attributes(edge).unwrap().thickness, Some(2.));
+
+    let html = node_by_label(&world, "<b>html</b>");
+    let edge = world.find_edge(node_by_label(&world, "fmt"), html).unwrap();
+    assert_eq!(world.edge_attributes(edge).unwrap().label.as_deref(), Some("very long"));
+}
+
+#[test]
+fn round_trips_positions_and_groups() {
+    let mut world = World::load_dot(fixture("dependencies.dot")).unwrap();
+
+    let mut output = vec![];
+    world.write_dot(&mut output).unwrap();
+    let reloaded = World::read_dot(std::str::from_utf8(&output).unwrap()).unwrap();
+
+    common::assert_round_trip(&world, &reloaded, 1e-4);
+
+    // A flat layout leaves out z and stays flat
+    let flat = world.nodes().map(|(id, node)| (id, node.pos * Vec3::new(1., 1., 0.))).collect::<Vec<_>>();
+    for (id, pos) in flat {
+        world.set_position(id, pos);
+    }
+    let mut output = vec![];
+    world.write_dot(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(!output.contains("z="));
+    let reloaded = World::read_dot(&output).unwrap();
+    common::assert_round_trip(&world, &reloaded, 1e-4);
+    assert!(reloaded.nodes().all(|(_, node)| node.pos.z == 0.));
+}
+
+#[test]
+fn undirected_graphs_reject_arrows() {
+    let world = World::read_dot("graph { a -- b -- c }").unwrap();
+    assert!(!world.is_directed());
+    assert_eq!(world.edge_count(), 2);
+
+    match World::read_dot("graph {\n  a -- b\n  b -> c\n}") {
+        Err(Error::Parse { line, .. }) => assert_eq!(line, 3),
+        other => panic!("expected a parse error, got {:?}", other.map(|w| w.node_count())),
+    }
+}
+
+#[test]
+fn escapes_round_trip() {
+    let mut world = World::empty();
+    let labels = ["back\\slash", "\"quoted\"", "two\nlines", "ends in \\", "\\n stays"];
+    for label in labels {
+        let id = world.add_node(mycelia::Node::new(Vec3::ZERO, 0.));
+        world.set_label(id, label);
+        world.attributes_mut(id).unwrap().properties.insert("note".to_string(), Value::from(label));
+    }
+
+    let mut output = vec![];
+    world.write_dot(&mut output).unwrap();
+    let reloaded = World::read_dot(std::str::from_utf8(&output).unwrap()).unwrap();
+    for label in labels {
+        let id = node_by_label(&reloaded, label);
+        assert_eq!(reloaded.property(id, "note"), Some(&Value::from(label)));
+    }
+}
+
+#[test]
+fn keeps_node_names_and_edge_ids() {
+    let mut world = World::read_dot(r#"digraph { a -> "core-macros" [id="uses"]; "node" }"#).unwrap();
+    world.add_node(mycelia::Node::new(Vec3::ZERO, 0.));
+
+    let mut output = vec![];
+    world.write_dot(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(r#"a -> "core-macros" [weight=1, id="uses""#));
+    // Keywords are quoted, nodes without a name are named by their slot
+    assert!(output.contains(r#"  "node" ["#));
+    assert!(output.contains("  n3 ["));
+
+    let reloaded = World::read_dot(&output).unwrap();
+    let ids = reloaded.nodes().map(|(id, _)| reloaded.attributes(id).unwrap().id.clone().unwrap()).collect::<Vec<_>>();
+    assert_eq!(ids, ["a", "core-macros", "node", "n3"]);
+    let (edge, _) = reloaded.edges().next().unwrap();
+    assert_eq!(reloaded.edge_attributes(edge).unwrap().id.as_deref(), Some("uses"));
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use glam::Vec3;
+use mycelia::io::Error;
+use mycelia::{Color, Node, Spell, TimedValue, Value, World};
+use common::{fixture, node_by_label};
+
+fn date(text: &str) -> Option<Value> {
+    let days = World::read_gexf(&format!(
+        r#"<gexf><graph timeformat="date"><nodes><node id="0" start="{}"/></nodes></graph></gexf>"#, text
+    )).unwrap();
+    let (id, _) = days.nodes().next().unwrap();
+    days.attributes(id).unwrap().spells[0].start.clone()
+}
+
+#[test]
+fn reads_attributes_viz_and_spells() {
+    let world = World::load_gexf(fixture("dynamic.gexf")).unwrap();
+
+    assert!(!world.is_directed());
+    assert_eq!(world.node_count(), 2);
+    assert_eq!(world.edge_count(), 1);
+
+    let gephi = node_by_label(&world, "Gephi");
+    let attributes = world.attributes(gephi).unwrap();
+    assert_eq!(attributes.kind.as_deref(), Some("software"));
+    assert_eq!(attributes.properties.get("members"), Some(&Value::Number(12.)));
+    assert_eq!(attributes.timeline, vec![
+        TimedValue { name: "members".to_string(), value: Value::Number(5.), spell: Spell { start: date("2008-01-01"), end: date("2009-12-31") } },
+        TimedValue { name: "members".to_string(), value: Value::Number(12.), spell: Spell { start: date("2010-01-01"), end: None } },
+    ]);
+    assert_eq!(attributes.id.as_deref(), Some("a"));
+    assert_eq!(attributes.properties.get("tags"), Some(&Value::from(vec!["graph", "viz"])));
+    assert_eq!(attributes.color, Some(Color { r: 239, g: 173, b: 66, a: 0.5 }));
+    assert_eq!(attributes.size, Some(3.25));
+    assert_eq!(world.node(gephi).unwrap().pos, Vec3::new(15.5, -40., 2.));
+    assert_eq!(attributes.spells, vec![Spell { start: date("2008-01-01"), end: None }]);
+    assert_eq!(attributes.spells[0].start.as_ref().and_then(Value::as_timestamp), Some(1_199_145_600_000));
+
+    // Defaults and multiple spells
+    let webatlas = node_by_label(&world, "Webatlas & co");
+    let attributes = world.attributes(webatlas).unwrap();
+    assert_eq!(attributes.properties.get("members"), Some(&Value::Number(1.)));
+    assert_eq!(attributes.spells.len(), 2);
+    assert_eq!(attributes.spells[1].end, None);
+
+    let edge = world.find_edge(gephi, webatlas).unwrap();
+    assert_eq!(world.edge(edge).unwrap().weight, 2.);
+    assert_eq!(world.relation(edge), Some("founded-by"));
+    assert_eq!(world.edge_attributes(edge).unwrap().thickness, Some(1.5));
+    assert_eq!(world.edge_attributes(edge).unwrap().spells, vec![Spell { start: None, end: date("2011-12-31") }]);
+}
+
+#[test]
+fn round_trips_everything() {
+    let world = World::load_gexf(fixture("dynamic.gexf")).unwrap();
+
+    let mut output = vec![];
+    world.write_gexf(&mut output).unwrap();
+    let reloaded = World::read_gexf(std::str::from_utf8(&output).unwrap()).unwrap();
+
+    common::assert_round_trip(&world, &reloaded, 0.);
+}
+
+#[test]
+fn keeps_ids_and_numbers_the_others() {
+    let mut world = World::load_gexf(fixture("dynamic.gexf")).unwrap();
+    let mut output = vec![];
+    world.write_gexf(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(r#"<node id="a""#));
+    assert!(output.contains(r#"<edge id="0" source="a" target="b""#));
+
+    // New nodes are numbered by slot, skipping ids that are taken
+    let (a, _) = world.nodes().next().unwrap();
+    world.attributes_mut(a).unwrap().id = Some("2".to_string());
+    world.add_node(Node::new(Vec3::ZERO, 0.));
+    let mut output = vec![];
+    world.write_gexf(&mut output).unwrap();
+    let reloaded = World::read_gexf(std::str::from_utf8(&output).unwrap()).unwrap();
+    let ids = reloaded.nodes().map(|(id, _)| reloaded.attributes(id).unwrap().id.clone().unwrap()).collect::<Vec<_>>();
+    assert_eq!(ids, ["2", "b", "2-1"]);
+}
+
+#[test]
+fn rejects_edges_to_unknown_nodes() {
+    let input = r#"<gexf><graph><nodes><node id="a"/></nodes><edges><edge source="a" target="b"/></edges></graph></gexf>"#;
+    assert!(matches!(World::read_gexf(input), Err(Error::Invalid(_))));
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use glam::Vec3;
+use mycelia::io::Error;
+use mycelia::{Node, Value, World};
+use common::{fixture, node_by_label};
+
+#[test]
+fn reads_nodes_edges_and_data() {
+    let world = World::load_graphml(fixture("knowledge.graphml")).unwrap();
+
+    assert!(world.is_directed());
+    assert_eq!(world.node_count(), 3);
+    assert_eq!(world.edge_count(), 2);
+
+    let graph = node_by_label(&world, "Graph");
+    assert_eq!(world.kind(graph), Some("concept"));
+    assert_eq!(world.node(graph).unwrap().pos, Vec3::new(0.25, -0.125, 0.5));
+    assert_eq!(world.property(graph, "year"), Some(&Value::Number(1736.)));
+    assert_eq!(world.property(graph, "verified"), Some(&Value::Bool(true)));
+
+    // Escaped text and key defaults
+    let tree = node_by_label(&world, "Tree & forest");
+    assert_eq!(world.property(tree, "verified"), Some(&Value::Bool(false)));
+
+    // A missing z coordinate is zero
+    let euler = node_by_label(&world, "Leonhard Euler");
+    assert_eq!(world.node(euler).unwrap().pos, Vec3::new(0.1, 0.2, 0.));
+
+    let is_a = world.find_edge(tree, graph).unwrap();
+    assert_eq!(world.relation(is_a), Some("is-a"));
+    assert_eq!(world.edge(is_a).unwrap().weight, 2.5);
+
+    let references = world.find_edge(euler, graph).unwrap();
+    assert_eq!(world.edge(references).unwrap().weight, 1.0);
+    assert_eq!(world.edge_attributes(references).unwrap().properties.get("source"), Some(&Value::from("Solutio problematis")));
+}
+
+#[test]
+fn round_trips_attributes_and_positions() {
+    let world = World::load_graphml(fixture("knowledge.graphml")).unwrap();
+
+    let mut output = vec![];
+    world.write_graphml(&mut output).unwrap();
+    let reloaded = World::read_graphml(std::str::from_utf8(&output).unwrap()).unwrap();
+
+    common::assert_round_trip(&world, &reloaded, 0.);
+}
+
+#[test]
+fn preserves_undirected_graphs() {
+    let world = World::load_graphml(fixture("undirected.graphml")).unwrap();
+    assert!(!world.is_directed());
+    assert_eq!(world.edge_count(), 2);
+
+    let mut output = vec![];
+    world.write_graphml(&mut output).unwrap();
+    let reloaded = World::read_graphml(std::str::from_utf8(&output).unwrap()).unwrap();
+    assert!(!reloaded.is_directed());
+}
+
+#[test]
+fn keeps_ids() {
+    let mut world = World::load_graphml(fixture("knowledge.graphml")).unwrap();
+    let graph = node_by_label(&world, "Graph");
+    assert_eq!(world.attributes(graph).unwrap().id.as_deref(), Some("graph"));
+    world.add_node(Node::new(Vec3::ZERO, 0.));
+
+    let mut output = vec![];
+    world.write_graphml(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(r#"<node id="graph">"#));
+    assert!(output.contains(r#"<node id="n3">"#));
+    // The fixture's edges have no ids and are written without
+    assert!(output.contains(r#"<edge source="tree" target="graph">"#));
+
+    let edited = World::read_graphml(r#"<graphml><graph><node id="a"/><edge id="link" source="a" target="a"/></graph></graphml>"#).unwrap();
+    let mut output = vec![];
+    edited.write_graphml(&mut output).unwrap();
+    assert!(String::from_utf8(output).unwrap().contains(r#"<edge id="link" source="a" target="a">"#));
+}
+
+#[test]
+fn rejects_edges_to_unknown_nodes() {
+    let input = r#"<graphml><graph edgedefault="directed"><node id="a"/><edge source="a" target="b"/></graph></graphml>"#;
+    assert!(matches!(World::read_graphml(input), Err(Error::Invalid(_))));
+}
+
+#[test]
+fn reports_the_line_of_malformed_documents() {
+    let input = "<graphml>\n<graph>\n<node id=\"a\">\n</graph>\n</graphml>";
+    match World::read_graphml(input) {
+        Err(Error::Parse { line, .. }) => assert_eq!(line, 4),
+        _ => panic!("expected a parse error"),
+    }
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use glam::Vec3;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use mycelia::io::Error;
+use mycelia::{CpuLayout, LayoutEngine, Node, RepulsionMode, World};
+use common::fixture;
+
+fn run(world: &mut World, mode: RepulsionMode, steps: usize) -> Vec<Vec3> {
+    let mut layout = CpuLayout::new(world);
+    layout.parameters.repulsion_mode = mode;
+    layout.parameters.theta = 0.;
+    layout.parameters.cutoff = 100.;
+    layout.run(&mut (), steps);
+    layout.positions().to_vec()
+}
+
+#[test]
+fn repulsion_modes_agree_when_exact() {
+    let mut world = World::new();
+    let all_pairs = run(&mut world, RepulsionMode::AllPairs, 20);
+    for mode in [RepulsionMode::BarnesHut, RepulsionMode::SpatialHash] {
+        let other = run(&mut world, mode, 20);
+        for (a, b) in all_pairs.iter().zip(other.iter()) {
+            assert!(a.distance(*b) < 1e-4, "{:?}: {} != {}", mode, a, b);
+        }
+    }
+}
+
+#[test]
+fn layout_settles() {
+    let mut world = World::new();
+    let mut layout = CpuLayout::new(&mut world);
+    layout.parameters.cooling = 0.99;
+    layout.run(&mut (), 1);
+    let first = layout.energy().unwrap();
+    layout.run(&mut (), 1000);
+    let last = layout.energy().unwrap();
+
+    assert!(last.kinetic_energy < first.kinetic_energy, "{:?} after {:?}", last, first);
+    assert!(last.max_displacement < 0.001, "{:?}", last);
+
+    layout.read_positions(&mut (), &mut world);
+    for ((_, node), p) in world.nodes().zip(layout.positions()) {
+        assert_eq!(node.pos, *p);
+    }
+}
+
+#[test]
+fn pinned_nodes_stay_and_edits_are_synced() {
+    let mut world = World::new();
+    let mut layout = CpuLayout::new(&mut world);
+    let pinned = world.node_at_slot(0).unwrap();
+    let start = world.node(pinned).unwrap().pos;
+    world.set_pinned(pinned, true);
+    layout.sync(&mut (), &mut world);
+    layout.run(&mut (), 50);
+    assert_eq!(layout.positions()[0], start);
+
+    let removed = world.node_at_slot(1).unwrap();
+    world.remove_node(removed);
+    layout.sync(&mut (), &mut world);
+    assert_eq!(layout.positions().len(), world.node_count());
+    layout.run(&mut (), 1);
+    assert_eq!(layout.positions()[0], start);
+}
+
+#[test]
+fn added_nodes_are_synced() {
+    let mut world = World::new();
+    let mut layout = CpuLayout::new(&mut world);
+    let count = world.node_count();
+
+    let lonely = world.add_node(Node::new(Vec3::new(1., 2., 3.), 0.));
+    let linked = world.add_node(Node::new(Vec3::new(-1., 0., 0.), 0.));
+    layout.sync(&mut (), &mut world);
+    assert_eq!(layout.positions().len(), count + 2);
+    assert_eq!(layout.positions()[count], Vec3::new(1., 2., 3.));
+    layout.run(&mut (), 1);
+
+    world.add_edge(linked, lonely);
+    world.add_node(Node::new(Vec3::ZERO, 0.));
+    layout.sync(&mut (), &mut world);
+    layout.run(&mut (), 10);
+    assert_eq!(layout.positions().len(), world.node_count());
+    assert!(layout.positions().iter().all(|p| p.is_finite()));
+}
+
+#[test]
+fn seeded_layouts_are_reproducible() {
+    let layout = |seed: u64| {
+        let mut world = World::load(fixture("dependencies.dot")).unwrap();
+        world.randomize_with(&mut StdRng::seed_from_u64(seed));
+        let mut layout = CpuLayout::new(&mut world);
+        layout.run(&mut (), 100);
+        layout.positions().to_vec()
+    };
+    assert_eq!(layout(42), layout(42));
+    assert_ne!(layout(42), layout(7));
+}
+
+#[test]
+fn formats_follow_the_extension() {
+    let world = World::load(fixture("knowledge.graphml")).unwrap();
+    assert_eq!(world.node_count(), 3);
+    assert!(World::load(fixture("dynamic.gexf")).unwrap().edge_count() > 0);
+    assert!(matches!(World::load("graph.txt"), Err(Error::Invalid(_))));
+    assert!(matches!(world.save("graph.csv"), Err(Error::Invalid(_))));
+}
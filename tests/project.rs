@@ -0,0 +1,76 @@
+mod common;
+
+use glam::{Mat4, Vec3};
+use mycelia::io::Error;
+use mycelia::project::VERSION;
+use mycelia::{Camera, PhysicsParameters, ProjectSettings, RepulsionMode, World};
+use common::{fixture, node_by_label};
+
+#[test]
+fn round_trips_graph_layout_and_settings() {
+    let mut world = World::load_gexf(fixture("dynamic.gexf")).unwrap();
+    let gephi = node_by_label(&world, "Gephi");
+    world.apply_layout([(Vec3::new(1., 2., 3.), Vec3::ZERO)]);
+    world.set_level(gephi, 0.5);
+    world.set_pinned(gephi, true);
+
+    let settings = ProjectSettings {
+        camera: Camera {
+            view_transform: Mat4::from_rotation_y(0.3),
+            camera_dist: 2.5,
+            perspective: false,
+        },
+        physics: PhysicsParameters {
+            repulsion_mode: RepulsionMode::SpatialHash,
+            repulsion: 0.7,
+            edge_attraction: 4.,
+            center_attraction: 0.02,
+            cutoff: 0.2,
+            damping: 0.3,
+            ..PhysicsParameters::default()
+        },
+    };
+
+    let mut output = vec![];
+    world.write_project(&settings, &mut output).unwrap();
+    let (reloaded, reloaded_settings) = World::read_project(std::str::from_utf8(&output).unwrap()).unwrap();
+
+    assert_eq!(reloaded_settings, settings);
+    common::assert_round_trip(&world, &reloaded, 0.);
+
+    for (id, node) in world.nodes() {
+        let other = node_by_label(&reloaded, world.label(id).unwrap());
+        assert_eq!(reloaded.node(other).unwrap().level, node.level);
+        assert_eq!(reloaded.is_pinned(other), node.pinned);
+    }
+    assert_eq!(reloaded.node(node_by_label(&reloaded, "Gephi")).unwrap().pos, Vec3::new(1., 2., 3.));
+    assert!(reloaded.is_pinned(node_by_label(&reloaded, "Gephi")));
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let newer = format!(r#"{{ "version": {}, "directed": true, "nodes": [], "edges": [] }}"#, VERSION + 1);
+    assert!(matches!(World::read_project(&newer), Err(Error::Invalid(_))));
+    assert!(matches!(World::read_project(r#"{ "nodes": [] }"#), Err(Error::Invalid(_))));
+    assert!(matches!(World::read_project("{\n  \"version\": 1,\n  oops\n}"), Err(Error::Parse { line: 3, .. })));
+}
+
+#[test]
+fn upgrades_version_1_physics() {
+    let v1 = r#"{
+        "version": 1,
+        "directed": true,
+        "nodes": [],
+        "edges": [],
+        "settings": {
+            "camera": { "view_transform": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1], "camera_dist": 1.2, "perspective": true },
+            "physics": { "repulsion": 0.7, "edge_attraction": 4.0, "center_attraction": 20000.0 }
+        }
+    }"#;
+    let (_, settings) = World::read_project(v1).unwrap();
+    assert_eq!(settings.physics, PhysicsParameters {
+        repulsion: 0.7,
+        edge_attraction: 4.,
+        ..PhysicsParameters::default()
+    });
+}
@@ -0,0 +1,55 @@
+use glam::{Mat4, Vec3};
+use mycelia::{rasterize, Node, RgbaImage, World};
+
+#[test]
+fn nodes_are_rings_and_edges_are_lines() {
+    let mut world = World::empty();
+    let a = world.add_node(Node::new(Vec3::new(-0.2, 0., 0.), 0.));
+    let b = world.add_node(Node::new(Vec3::new(0.2, 0., 0.), 0.));
+    world.add_edge(a, b);
+
+    let image = rasterize(&world, Mat4::IDENTITY, 100, 100);
+    assert_eq!(image.pixel(0, 0), [0, 0, 0, 255]);
+    // Ring of radius 1.1 * 12 around the node at x = 30
+    assert_eq!(image.pixel(30, 63), [255, 255, 255, 255]);
+    assert_eq!(image.pixel(30, 40), [0, 0, 0, 255]);
+    // Edge in the colour of nodes without density
+    assert_eq!(image.pixel(50, 50), [51, 0, 51, 255]);
+}
+
+#[test]
+fn nodes_behind_the_camera_are_skipped() {
+    let mut world = World::empty();
+    world.add_node(Node::new(Vec3::ZERO, 1.));
+    let transform = Mat4::perspective_rh(1.2, 1., 0.01, 10.) * Mat4::from_translation(Vec3::new(0., 0., 1.));
+    assert_eq!(rasterize(&world, transform, 64, 64), RgbaImage::new(64, 64));
+}
+
+#[test]
+fn png_round_trip() {
+    let mut world = World::empty();
+    world.add_node(Node::new(Vec3::ZERO, 0.5));
+    let image = rasterize(&world, Mat4::IDENTITY, 40, 30);
+
+    let mut png = vec![];
+    image.write_png(&mut png).unwrap();
+    let mut reader = png::Decoder::new(std::io::Cursor::new(png)).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (40, 30));
+    assert_eq!(pixels, image.pixels);
+}
+
+#[test]
+fn lines_are_clipped_at_any_resolution() {
+    let mut world = World::empty();
+    // At 4000 pixels, x = 0.1 is at pixel 2400 and x = 0.9 far off the image at 5600
+    let a = world.add_node(Node::new(Vec3::new(0.1, 0., 0.), 0.));
+    let b = world.add_node(Node::new(Vec3::new(0.9, 0., 0.), 0.));
+    world.add_edge(a, b);
+
+    let image = rasterize(&world, Mat4::IDENTITY, 4000, 4000);
+    assert_eq!(image.pixel(3500, 2000), [51, 0, 51, 255]);
+    assert_eq!(image.pixel(3999, 2000), [51, 0, 51, 255]);
+    assert_eq!(image.pixel(2000, 2000), [0, 0, 0, 255]);
+}
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+use mycelia::{Recorder, RgbaImage};
+
+fn output(name: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+#[test]
+fn every_nth_frame_is_numbered() {
+    let directory = output("sequence");
+    let mut recorder = Recorder::to_directory(&directory, 8, 4, 3).unwrap();
+    for _ in 0..7 {
+        if recorder.next_frame() {
+            recorder.capture(&RgbaImage::new(8, 4)).unwrap();
+        }
+    }
+    assert_eq!(recorder.captured(), 3);
+    recorder.finish().unwrap();
+
+    let mut files = std::fs::read_dir(&directory).unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["frame_00000.png", "frame_00001.png", "frame_00002.png"]);
+}
+
+#[test]
+fn frames_of_another_size_are_rejected() {
+    let mut recorder = Recorder::to_directory(output("size"), 8, 4, 1).unwrap();
+    assert!(recorder.capture(&RgbaImage::new(4, 8)).is_err());
+    assert_eq!(recorder.captured(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn raw_frames_are_piped_to_the_encoder() {
+    let directory = output("encoder");
+    std::fs::create_dir_all(&directory).unwrap();
+    let file = directory.join("frames_{width}x{height}.rgba");
+    let mut recorder = Recorder::to_encoder(&format!("dd status=none of={}", file.display()), 8, 4, 1).unwrap();
+    for _ in 0..2 {
+        assert!(recorder.next_frame());
+        recorder.capture(&RgbaImage::new(8, 4)).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let raw = std::fs::read(directory.join("frames_8x4.rgba")).unwrap();
+    assert_eq!(raw, [0, 0, 0, 255].repeat(2 * 8 * 4));
+}
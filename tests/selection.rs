@@ -0,0 +1,66 @@
+use mycelia::{Node, SelectionMode, World};
+
+#[test]
+fn combines_picks_with_the_selection() {
+    let mut world = World::empty();
+    let ids = (0..4).map(|_| world.add_node(Node::new_random(0.))).collect::<Vec<_>>();
+
+    world.selection_mut().combine([ids[0], ids[1]], SelectionMode::Replace);
+    world.selection_mut().combine([ids[2]], SelectionMode::Add);
+    assert_eq!(world.selection().as_slice(), &[ids[0], ids[1], ids[2]]);
+
+    world.selection_mut().combine([ids[1], ids[3]], SelectionMode::Toggle);
+    assert_eq!(world.selection().as_slice(), &[ids[0], ids[2], ids[3]]);
+
+    world.selection_mut().combine([ids[1]], SelectionMode::Replace);
+    assert_eq!(world.selection().as_slice(), &[ids[1]]);
+
+    world.invert_selection();
+    assert!(!world.is_selected(ids[1]));
+    assert_eq!(world.selection().len(), 3);
+
+    world.select_all();
+    assert_eq!(world.selection().len(), 4);
+    assert_eq!(world.selection().first(), Some(ids[0]));
+}
+
+#[test]
+fn removing_nodes_updates_the_selection() {
+    let mut world = World::empty();
+    let a = world.add_node(Node::new_random(0.));
+    let b = world.add_node(Node::new_random(0.));
+    let c = world.add_node(Node::new_random(0.));
+    world.add_edge(a, c);
+    world.take_changes();
+
+    world.selection_mut().extend([a, b]);
+    assert!(world.take_changes().selection);
+
+    world.remove_selected();
+    assert_eq!(world.node_count(), 1);
+    assert_eq!(world.edge_count(), 0);
+    assert!(world.selection().is_empty());
+    assert!(world.node(c).is_some());
+}
+
+#[test]
+fn removing_moves_the_last_selected_node() {
+    let mut world = World::empty();
+    let ids = (0..5).map(|_| world.add_node(Node::new_random(0.))).collect::<Vec<_>>();
+    world.select_all();
+
+    assert!(world.selection_mut().remove(ids[1]));
+    assert!(!world.selection_mut().remove(ids[1]));
+    assert_eq!(world.selection().as_slice(), &[ids[0], ids[4], ids[2], ids[3]]);
+
+    // The moved node can still be removed, and the last one moves nothing
+    assert!(world.selection_mut().remove(ids[4]));
+    assert!(world.selection_mut().remove(ids[2]));
+    assert_eq!(world.selection().as_slice(), &[ids[0], ids[3]]);
+    assert!(world.is_selected(ids[3]));
+    assert!(!world.is_selected(ids[4]));
+
+    world.invert_selection();
+    assert_eq!(world.selection().len(), 3);
+    assert!(world.is_selected(ids[4]));
+}
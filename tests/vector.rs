@@ -0,0 +1,62 @@
+use glam::{Mat4, Vec3};
+use mycelia::vector::Shape;
+use mycelia::{vectorize, Node, World};
+
+fn camera() -> Mat4 {
+    Mat4::perspective_rh(1.2, 1., 0.01, 10.) * Mat4::from_translation(Vec3::new(0., 0., -1.2))
+}
+
+#[test]
+fn shapes_are_sorted_back_to_front() {
+    let mut world = World::empty();
+    let near = world.add_node(Node::new(Vec3::new(0., 0., 0.5), 0.));
+    let far = world.add_node(Node::new(Vec3::new(0., 0., -0.5), 1.));
+    world.add_edge(near, far);
+    world.set_label(near, "near");
+
+    let image = vectorize(&world, camera(), 200, 200);
+    let radii = image.shapes.iter()
+        .filter_map(|shape| match shape {
+            Shape::Circle { radius, fill: Some(_), .. } => Some(*radius),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(radii.len(), 2);
+    assert!(radii[0] < radii[1], "{:?}", radii);
+
+    // The far node with density, the edge, then the near node and its label
+    assert_eq!(image.shapes.len(), 1 + 3 + 1 + 1 + 1);
+    assert!(matches!(image.shapes[4], Shape::Line { .. }));
+    assert!(matches!(&image.shapes[6], Shape::Label { text, .. } if text == "near"));
+}
+
+#[test]
+fn svg_and_pdf_contain_the_shapes() {
+    let mut world = World::empty();
+    let a = world.add_node(Node::new(Vec3::new(-0.3, 0., 0.), 1.));
+    let b = world.add_node(Node::new(Vec3::new(0.3, 0., 0.), 0.));
+    world.add_edge(a, b);
+    world.set_label(a, "<a & (b)>");
+    let image = vectorize(&world, camera(), 300, 200);
+
+    let svg = image.to_svg();
+    assert!(svg.starts_with("<svg"));
+    assert_eq!(svg.matches("<circle").count(), 5);
+    assert_eq!(svg.matches("<line ").count(), 1);
+    assert!(svg.contains("linearGradient"));
+    assert!(svg.contains("&lt;a &amp; (b)&gt;"));
+
+    let pdf = image.to_pdf();
+    let text = String::from_utf8(pdf).unwrap();
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.contains("/MediaBox [0 0 300 200]"));
+    assert!(text.contains("(<a & \\(b\\)>) Tj"));
+    let xref = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse::<usize>().unwrap();
+    assert!(text[xref..].starts_with("xref"));
+}
+
+#[test]
+fn format_follows_the_extension() {
+    let image = vectorize(&World::empty(), camera(), 10, 10);
+    assert_eq!(image.save("view.png").unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,119 @@
+use glam::Vec3;
+use mycelia::world::DenseSlots;
+use mycelia::{Node, NodeId, World};
+
+fn nodes(world: &mut World, count: usize) -> Vec<NodeId> {
+    (0..count).map(|i| world.add_node(Node::new(Vec3::splat(i as f32), 0.))).collect()
+}
+
+#[test]
+fn removing_swaps_the_last_key_into_the_freed_slot() {
+    let mut world = World::empty();
+    let ids = nodes(&mut world, 4);
+
+    let mut slots = DenseSlots::default();
+    for id in &ids {
+        slots.insert(*id);
+    }
+    assert_eq!(slots.remove(ids[1]), Some(1));
+    assert_eq!(slots.keys(), &[ids[0], ids[3], ids[2]]);
+    assert_eq!(slots.slot(ids[3]), Some(1));
+    assert_eq!(slots.slot(ids[1]), None);
+
+    // Removing the last key moves nothing
+    assert_eq!(slots.remove(ids[2]), Some(2));
+    assert_eq!(slots.keys(), &[ids[0], ids[3]]);
+    assert_eq!(slots.remove(ids[2]), None);
+}
+
+#[test]
+fn removing_a_node_from_the_middle_moves_the_last_slot() {
+    let mut world = World::empty();
+    let ids = nodes(&mut world, 4);
+    world.add_edge(ids[0], ids[1]);
+    world.add_edge(ids[3], ids[0]);
+    world.take_changes();
+
+    let removed = world.remove_node(ids[1]).unwrap();
+    assert_eq!(removed.pos, Vec3::splat(1.));
+    assert_eq!(world.node_count(), 3);
+    assert_eq!(world.edge_count(), 1);
+    assert!(world.remove_node(ids[1]).is_none());
+
+    // The node of the last slot now lives in the freed one
+    assert_eq!(world.node_slot(ids[3]), Some(1));
+    assert_eq!(world.node_at_slot(1), Some(ids[3]));
+    assert_eq!(world.node_at_slot(3), None);
+    assert_eq!(world.dense_edges().collect::<Vec<_>>(), vec![(1, 0)]);
+
+    let changes = world.take_changes();
+    assert_eq!(changes.moved, vec![(3, 1)]);
+    assert!(changes.nodes.is_empty());
+    assert!(changes.edges);
+}
+
+#[test]
+fn changed_nodes_follow_their_slot() {
+    let mut world = World::empty();
+    let ids = nodes(&mut world, 4);
+    world.take_changes();
+
+    world.set_position(ids[3], Vec3::ONE);
+    world.set_position(ids[1], Vec3::ONE);
+    world.remove_node(ids[1]);
+
+    // The removed node's change is dropped, the moved node's is now at its new slot
+    let changes = world.take_changes();
+    assert_eq!(changes.moved, vec![(3, 1)]);
+    assert_eq!(changes.nodes.iter().copied().collect::<Vec<_>>(), vec![1]);
+
+    // Moves are recorded in order, the change follows a node that moves twice
+    let mut world = World::empty();
+    let ids = nodes(&mut world, 4);
+    world.take_changes();
+
+    world.set_position(ids[3], Vec3::ONE);
+    world.remove_node(ids[2]);
+    world.remove_node(ids[0]);
+    let changes = world.take_changes();
+    assert_eq!(changes.moved, vec![(3, 2), (2, 0)]);
+    assert_eq!(changes.nodes.iter().copied().collect::<Vec<_>>(), vec![0]);
+    assert_eq!(world.node_at_slot(0), Some(ids[3]));
+}
+
+#[test]
+fn taking_changes_clears_them() {
+    let mut world = World::empty();
+    let ids = nodes(&mut world, 3);
+    world.add_edge(ids[0], ids[1]);
+    world.set_pinned(ids[2], true);
+    world.remove_node(ids[0]);
+
+    let changes = world.take_changes();
+    assert!(!changes.is_empty());
+    assert!(changes.edges);
+    assert!(changes.pinned);
+    assert!(world.take_changes().is_empty());
+}
+
+#[test]
+fn mutable_access_marks_nodes_as_changed() {
+    let mut world = World::empty();
+    let ids = nodes(&mut world, 3);
+    world.take_changes();
+
+    world.node_mut(ids[1]).unwrap().pinned = true;
+    let changes = world.take_changes();
+    assert_eq!(changes.nodes.iter().copied().collect::<Vec<_>>(), vec![1]);
+    assert!(changes.pinned);
+
+    for (_, node) in world.nodes_mut() {
+        node.pos = Vec3::ZERO;
+    }
+    assert_eq!(world.take_changes().nodes.len(), 3);
+
+    // Levels are uploaded on their own
+    world.set_level(ids[2], 1.);
+    assert_eq!(world.node(ids[2]).unwrap().level, 1.);
+    assert!(world.take_changes().is_empty());
+}